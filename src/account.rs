@@ -2,7 +2,10 @@
 
 pub mod account_hasher;
 pub mod accounts_db;
+pub mod accounts_merkle_tree;
 pub mod debug_account_data;
+pub mod merkle_proof;
+pub mod merkle_root_builder;
 
 use core::cell::{Ref, RefCell};
 use core::mem::MaybeUninit;
@@ -10,6 +13,7 @@ use core::{fmt, ptr};
 use std::rc::Rc;
 use std::sync::Arc;
 
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use debug_account_data::debug_account_data;
 use serde::{Deserialize, Serialize, Serializer};
 
@@ -19,7 +23,10 @@ use crate::pubkey::Pubkey;
 
 /// An Account with data that is stored on chain
 #[repr(C)]
-#[derive(Deserialize, PartialEq, Eq, Clone, Default)]
+#[derive(
+    BorshSerialize, BorshDeserialize, BorshSchema, Deserialize, PartialEq, Eq, Clone, Default,
+)]
+#[borsh(crate = "borsh")]
 #[serde(rename_all = "camelCase")]
 pub struct Account {
     /// lamports in the account
@@ -648,3 +655,44 @@ impl AccountSharedData {
         shared_serialize_data(self, state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+
+    use super::*;
+
+    #[test]
+    fn test_account_borsh() {
+        let owner = Pubkey::new_unique();
+        let mut account = Account::new_rent_epoch(42, 3, &owner, 7);
+        account.data.copy_from_slice(&[1, 2, 3]);
+        account.executable = true;
+
+        let mut bytes = 42u64.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 2, 3]);
+        bytes.extend_from_slice(owner.as_ref());
+        bytes.push(1);
+        bytes.extend_from_slice(&7u64.to_le_bytes());
+        assert_eq!(borsh::to_vec(&account).unwrap(), bytes);
+        assert_eq!(Account::try_from_slice(&bytes).unwrap(), account);
+        assert!(Account::try_from_slice(&bytes[..bytes.len() - 1]).is_err());
+
+        let container = BorshSchemaContainer::for_type::<Account>();
+        assert_eq!(container.declaration(), "Account");
+        let field = |name: &str, declaration: &str| (name.to_string(), declaration.to_string());
+        assert_eq!(
+            container.get_definition("Account"),
+            Some(&Definition::Struct {
+                fields: Fields::NamedFields(vec![
+                    field("lamports", "u64"),
+                    field("data", "Vec<u8>"),
+                    field("owner", "Pubkey"),
+                    field("executable", "bool"),
+                    field("rent_epoch", "u64"),
+                ])
+            })
+        );
+    }
+}
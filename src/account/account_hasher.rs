@@ -1,21 +1,85 @@
 use bytemuck::{Pod, Zeroable};
 
-use crate::hash::{Hash, Hasher};
+use crate::hash::{GenericHasher, Hash, HashFunction, Sha256};
 use crate::pubkey::Pubkey;
 
 pub const MERKLE_FANOUT: usize = 16;
 
+/// Minimum number of chunks in a level for its chunks to be hashed in parallel.
+#[cfg(feature = "rayon")]
+const MIN_CHUNKS_FOR_PARALLEL_HASHING: usize = 256;
+
 /// Hash of an account
 #[repr(transparent)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Pod, Zeroable)]
 pub struct AccountHash(pub Hash);
 
+/// Prefix of a leaf in [`MerkleTreeMode::DomainSeparated`].
+pub const LEAF_PREFIX: &[u8] = &[0];
+/// Prefix of an inner node in [`MerkleTreeMode::DomainSeparated`].
+pub const INTERMEDIATE_PREFIX: &[u8] = &[1];
+
+/// How the nodes of the merkle tree built by [`AccountsHasher`] are hashed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MerkleTreeMode {
+    /// Leaves are used as is, and every inner node is the hash of its
+    /// children. This is how the accounts hash is computed.
+    #[default]
+    Legacy,
+    /// Leaves are hashed with [`LEAF_PREFIX`], and inner nodes hash their
+    /// children with [`INTERMEDIATE_PREFIX`], as in `solana-merkle-tree`. A
+    /// leaf can then never be passed off as an inner node or the other way
+    /// around.
+    DomainSeparated,
+}
+
+impl MerkleTreeMode {
+    /// The lowest tree node for `leaf`.
+    pub(crate) fn leaf_node<H: HashFunction>(self, leaf: &Hash) -> Hash {
+        match self {
+            MerkleTreeMode::Legacy => *leaf,
+            MerkleTreeMode::DomainSeparated => {
+                let mut hasher = GenericHasher::<H>::default();
+                hasher.hashv(&[LEAF_PREFIX, leaf.as_ref()]);
+                hasher.result()
+            }
+        }
+    }
+
+    /// A hasher for an inner node, to be fed its children.
+    pub(crate) fn intermediate_hasher<H: HashFunction>(self) -> GenericHasher<H> {
+        let mut hasher = GenericHasher::<H>::default();
+        if self == MerkleTreeMode::DomainSeparated {
+            hasher.hash(INTERMEDIATE_PREFIX);
+        }
+        hasher
+    }
+}
+
 #[derive(Debug)]
 pub struct AccountsHasher;
 
 impl AccountsHasher {
     pub fn compute_merkle_root(hashes: Vec<(Pubkey, Hash)>, fanout: usize) -> Hash {
-        Self::compute_merkle_root_loop(hashes, fanout, |t| &t.1)
+        Self::compute_merkle_root_with::<Sha256>(hashes, fanout)
+    }
+
+    pub fn compute_merkle_root_with<H: HashFunction>(
+        hashes: Vec<(Pubkey, Hash)>,
+        fanout: usize,
+    ) -> Hash {
+        Self::compute_merkle_root_loop_with::<H, _, _>(hashes, fanout, |t| &t.1)
+    }
+
+    /// Same as [`compute_merkle_root`], hashing the tree in the given `mode`.
+    ///
+    /// [`compute_merkle_root`]: AccountsHasher::compute_merkle_root
+    pub fn compute_merkle_root_with_mode(
+        hashes: Vec<(Pubkey, Hash)>,
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Hash {
+        Self::compute_merkle_root_level::<Sha256, _, _>(hashes, fanout, |t| &t.1, mode, true)
     }
 
     // For the first iteration, there could be more items in the tuple than just hash and lamports.
@@ -24,9 +88,46 @@ impl AccountsHasher {
     where
         F: Fn(&T) -> &Hash + std::marker::Sync,
         T: std::marker::Sync,
+    {
+        Self::compute_merkle_root_loop_with::<Sha256, T, F>(hashes, fanout, extractor)
+    }
+
+    /// Same as [`compute_merkle_root_loop`], hashing each node with `H` instead of SHA-256.
+    ///
+    /// [`compute_merkle_root_loop`]: AccountsHasher::compute_merkle_root_loop
+    pub fn compute_merkle_root_loop_with<H, T, F>(
+        hashes: Vec<T>,
+        fanout: usize,
+        extractor: F,
+    ) -> Hash
+    where
+        H: HashFunction,
+        F: Fn(&T) -> &Hash + std::marker::Sync,
+        T: std::marker::Sync,
+    {
+        Self::compute_merkle_root_level::<H, T, F>(
+            hashes,
+            fanout,
+            extractor,
+            MerkleTreeMode::Legacy,
+            true,
+        )
+    }
+
+    fn compute_merkle_root_level<H, T, F>(
+        hashes: Vec<T>,
+        fanout: usize,
+        extractor: F,
+        mode: MerkleTreeMode,
+        is_leaf_level: bool,
+    ) -> Hash
+    where
+        H: HashFunction,
+        F: Fn(&T) -> &Hash + std::marker::Sync,
+        T: std::marker::Sync,
     {
         if hashes.is_empty() {
-            return Hasher::default().result();
+            return mode.intermediate_hasher::<H>().result();
         }
 
         let start_time = std::time::Instant::now();
@@ -34,45 +135,181 @@ impl AccountsHasher {
         let total_hashes = hashes.len();
         let chunks = Self::div_ceil(total_hashes, fanout);
 
-        let result: Vec<_> = (0..chunks)
-            .map(|i| {
-                let start_index = i * fanout;
-                let end_index = std::cmp::min(start_index + fanout, total_hashes);
+        let hash_chunk = |i: usize| {
+            let start_index = i * fanout;
+            let end_index = std::cmp::min(start_index + fanout, total_hashes);
 
-                let mut hasher = Hasher::default();
-                for item in hashes.iter().take(end_index).skip(start_index) {
-                    let h = extractor(item);
+            let mut hasher = mode.intermediate_hasher::<H>();
+            for item in hashes.iter().take(end_index).skip(start_index) {
+                let h = extractor(item);
+                if is_leaf_level {
+                    hasher.hash(mode.leaf_node::<H>(h).as_ref());
+                } else {
                     hasher.hash(h.as_ref());
                 }
+            }
 
-                hasher.result()
-            })
-            .collect();
+            hasher.result()
+        };
+
+        #[cfg(feature = "rayon")]
+        let result: Vec<_> = if chunks >= MIN_CHUNKS_FOR_PARALLEL_HASHING {
+            use rayon::prelude::*;
+            (0..chunks).into_par_iter().map(hash_chunk).collect()
+        } else {
+            (0..chunks).map(hash_chunk).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let result: Vec<_> = (0..chunks).map(hash_chunk).collect();
         let elapsed_time = start_time.elapsed();
         log::debug!("hashing {} {:?}", total_hashes, elapsed_time);
 
         if result.len() == 1 {
             result[0]
         } else {
-            Self::compute_merkle_root_recurse(result, fanout)
+            Self::compute_merkle_root_recurse_level::<H>(result, fanout, mode)
         }
     }
 
+    // this function avoids an infinite recursion compiler error
+    fn compute_merkle_root_recurse_level<H: HashFunction>(
+        hashes: Vec<Hash>,
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Hash {
+        Self::compute_merkle_root_level::<H, _, _>(hashes, fanout, |t| t, mode, false)
+    }
+
     // this function avoids an infinite recursion compiler error
     pub fn compute_merkle_root_recurse(hashes: Vec<Hash>, fanout: usize) -> Hash {
-        Self::compute_merkle_root_loop(hashes, fanout, |t| t)
+        Self::compute_merkle_root_recurse_with::<Sha256>(hashes, fanout)
+    }
+
+    pub fn compute_merkle_root_recurse_with<H: HashFunction>(
+        hashes: Vec<Hash>,
+        fanout: usize,
+    ) -> Hash {
+        Self::compute_merkle_root_loop_with::<H, _, _>(hashes, fanout, |t| t)
     }
 
     pub fn div_ceil(x: usize, y: usize) -> usize {
-        let mut result = x / y;
-        if x % y != 0 {
-            result += 1;
-        }
-        result
+        x.div_ceil(y)
     }
 
     pub fn accumulate_account_hashes(mut hashes: Vec<(Pubkey, AccountHash)>) -> Hash {
-        hashes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        hashes.sort_unstable_by_key(|a| a.0);
         Self::compute_merkle_root_loop(hashes, MERKLE_FANOUT, |i| &i.1 .0)
     }
+
+    /// Same as [`accumulate_account_hashes`], hashing the tree in the given
+    /// `mode`.
+    ///
+    /// [`accumulate_account_hashes`]: AccountsHasher::accumulate_account_hashes
+    pub fn accumulate_account_hashes_with_mode(
+        mut hashes: Vec<(Pubkey, AccountHash)>,
+        mode: MerkleTreeMode,
+    ) -> Hash {
+        hashes.sort_unstable_by_key(|a| a.0);
+        Self::compute_merkle_root_level::<Sha256, _, _>(
+            hashes,
+            MERKLE_FANOUT,
+            |i| &i.1 .0,
+            mode,
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference_root(hashes: &[Hash], fanout: usize) -> Hash {
+        let parents: Vec<Hash> = hashes
+            .chunks(fanout)
+            .map(|chunk| crate::hash::hashv(&chunk.iter().map(|h| h.as_ref()).collect::<Vec<_>>()))
+            .collect();
+        if parents.len() == 1 {
+            parents[0]
+        } else {
+            reference_root(&parents, fanout)
+        }
+    }
+
+    #[test]
+    fn test_compute_merkle_root() {
+        // large enough for the lower levels to be hashed in parallel with rayon
+        for count in [1, 2, MERKLE_FANOUT, MERKLE_FANOUT + 1, 100_000] {
+            let hashes: Vec<_> = (0..count)
+                .map(|i: usize| crate::hash::hash(&i.to_le_bytes()))
+                .collect();
+            let expected = reference_root(&hashes, MERKLE_FANOUT);
+            assert_eq!(
+                AccountsHasher::compute_merkle_root_recurse(hashes.clone(), MERKLE_FANOUT),
+                expected
+            );
+            let with_pubkeys = hashes
+                .into_iter()
+                .map(|h| (Pubkey::new_unique(), h))
+                .collect();
+            assert_eq!(
+                AccountsHasher::compute_merkle_root(with_pubkeys, MERKLE_FANOUT),
+                expected
+            );
+        }
+        assert_eq!(
+            AccountsHasher::compute_merkle_root(vec![], MERKLE_FANOUT),
+            crate::hash::Hasher::default().result()
+        );
+    }
+
+    #[test]
+    fn test_compute_merkle_root_domain_separated() {
+        let leaf = |i: usize| crate::hash::hash(&i.to_le_bytes());
+        let node = |children: &[Hash]| {
+            let mut vals = vec![INTERMEDIATE_PREFIX];
+            vals.extend(children.iter().map(|h| h.as_ref()));
+            crate::hash::hashv(&vals)
+        };
+        let leaf_node = |leaf: Hash| crate::hash::hashv(&[LEAF_PREFIX, leaf.as_ref()]);
+
+        let hashes: Vec<_> = (0..3).map(|i| (Pubkey::new_unique(), leaf(i))).collect();
+        let expected = node(&[
+            leaf_node(hashes[0].1),
+            leaf_node(hashes[1].1),
+            leaf_node(hashes[2].1),
+        ]);
+        let root = AccountsHasher::compute_merkle_root_with_mode(
+            hashes.clone(),
+            MERKLE_FANOUT,
+            MerkleTreeMode::DomainSeparated,
+        );
+        assert_eq!(root, expected);
+        assert_ne!(
+            root,
+            AccountsHasher::compute_merkle_root(hashes.clone(), MERKLE_FANOUT)
+        );
+        assert_eq!(
+            AccountsHasher::compute_merkle_root_with_mode(
+                hashes.clone(),
+                MERKLE_FANOUT,
+                MerkleTreeMode::Legacy
+            ),
+            AccountsHasher::compute_merkle_root(hashes, MERKLE_FANOUT)
+        );
+
+        // leaves are only prefixed on the lowest level
+        let hashes: Vec<_> = (0..5).map(|i| (Pubkey::new_unique(), leaf(i))).collect();
+        let leaf_nodes: Vec<_> = hashes.iter().map(|(_, h)| leaf_node(*h)).collect();
+        let level: Vec<_> = leaf_nodes.chunks(2).map(node).collect();
+        let level: Vec<_> = level.chunks(2).map(node).collect();
+        assert_eq!(
+            AccountsHasher::compute_merkle_root_with_mode(
+                hashes,
+                2,
+                MerkleTreeMode::DomainSeparated
+            ),
+            node(&level)
+        );
+    }
 }
@@ -1,25 +1,269 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Weak};
+
+use parking_lot::RwLock;
 use smallvec::SmallVec;
 
 use super::account_hasher::AccountHash;
-use crate::account::ReadableAccount;
-use crate::clock::Epoch;
-use crate::hash::Hash;
+use crate::account::{AccountSharedData, ReadableAccount};
+use crate::clock::{Epoch, Slot};
+use crate::hash::{Blake3, GenericHasher, Hash, HashFunction, Sha256};
+use crate::lt_hash::LtHash;
 use crate::pubkey::Pubkey;
 
+/// The ways account hashes have been computed over Solana's history.
+///
+/// Every version hashes the lamports, the slot if included, the rent epoch,
+/// the data, the executable flag, the owner if included and the pubkey, in that
+/// order. This follows `hash_account_data` and `blake3_hash_account_data` in
+/// `runtime/src/accounts_db.rs` of Solana 1.4, whose `include_owner` flag
+/// leaves the owner out of hashes from before it was added, and
+/// `AccountsDb::hash_account_data` in Solana 1.16, whose `IncludeSlotInHash`
+/// leaves the slot out once `account_hash_ignore_slot` is active. Neither
+/// hashes the account's write version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccountHashVersion {
+    /// SHA-256, including the slot but not the owner.
+    Sha256WithSlotWithoutOwner,
+    /// SHA-256, including the slot. Used before blake3 was adopted.
+    Sha256WithSlot,
+    /// blake3, including the slot but not the owner.
+    Blake3WithSlotWithoutOwner,
+    /// blake3, including the slot. Used until the slot was removed from the
+    /// hash, which lets an unchanged account keep its hash across slots.
+    Blake3WithSlot,
+    /// blake3, without the slot.
+    #[default]
+    Blake3,
+}
+
 pub fn hash_account<T: ReadableAccount>(account: &T, pubkey: &Pubkey) -> AccountHash {
-    hash_account_data(
-        account.lamports(),
-        account.owner(),
-        account.executable(),
-        account.rent_epoch(),
+    hash_account_with_version(account, pubkey, 0, AccountHashVersion::Blake3)
+}
+
+/// Hash an account stored in `slot` as it was hashed in `version`, to verify
+/// accounts hashes from older snapshots.
+///
+/// `slot` is ignored by [`AccountHashVersion::Blake3`].
+pub fn hash_account_with_version<T: ReadableAccount>(
+    account: &T,
+    pubkey: &Pubkey,
+    slot: Slot,
+    version: AccountHashVersion,
+) -> AccountHash {
+    fn hash_with<H: HashFunction, T: ReadableAccount>(
+        account: &T,
+        pubkey: &Pubkey,
+        slot: Option<Slot>,
+        include_owner: bool,
+    ) -> AccountHash {
+        hash_account_data::<H>(
+            account.lamports(),
+            slot,
+            include_owner.then(|| account.owner()),
+            account.executable(),
+            account.rent_epoch(),
+            account.data(),
+            pubkey,
+        )
+    }
+
+    match version {
+        AccountHashVersion::Sha256WithSlotWithoutOwner => {
+            hash_with::<Sha256, _>(account, pubkey, Some(slot), false)
+        }
+        AccountHashVersion::Sha256WithSlot => {
+            hash_with::<Sha256, _>(account, pubkey, Some(slot), true)
+        }
+        AccountHashVersion::Blake3WithSlotWithoutOwner => {
+            hash_with::<Blake3, _>(account, pubkey, Some(slot), false)
+        }
+        AccountHashVersion::Blake3WithSlot => {
+            hash_with::<Blake3, _>(account, pubkey, Some(slot), true)
+        }
+        AccountHashVersion::Blake3 => hash_with::<Blake3, _>(account, pubkey, None, true),
+    }
+}
+
+/// Minimum number of accounts in a batch for them to be hashed in parallel.
+#[cfg(feature = "rayon")]
+const MIN_ACCOUNTS_FOR_PARALLEL_HASHING: usize = 64;
+
+/// Length of the preimage of an account hash without its data.
+const ACCOUNT_PREIMAGE_FIELDS_LEN: usize =
+    8 /* lamports */ + 8 /* rent_epoch */ + 1 /* exec_flag */ + 32 /* owner_key */ + 32 /* pubkey */;
+
+/// Hash a batch of accounts, as [`hash_account`] does for each of them.
+///
+/// Accounts whose preimage fits in a single blake3 chunk, i.e. with up to
+/// `CHUNK_LEN - 81` bytes of data, are hashed several at a time in SIMD lanes
+/// with [`blake3::hash_many`]; larger accounts are hashed one by one. With the
+/// `rayon` feature, large batches are also spread across threads.
+///
+/// [`blake3::hash_many`]: crate::blake3::hash_many
+pub fn hash_accounts<T: ReadableAccount + Sync>(accounts: &[(Pubkey, &T)]) -> Vec<AccountHash> {
+    #[cfg(feature = "rayon")]
+    if accounts.len() >= MIN_ACCOUNTS_FOR_PARALLEL_HASHING {
+        use rayon::prelude::*;
+        return accounts
+            .par_chunks(MIN_ACCOUNTS_FOR_PARALLEL_HASHING)
+            .flat_map_iter(hash_accounts_in_lanes)
+            .collect();
+    }
+    hash_accounts_in_lanes(accounts)
+}
+
+fn hash_accounts_in_lanes<T: ReadableAccount>(accounts: &[(Pubkey, &T)]) -> Vec<AccountHash> {
+    let mut hashes = vec![AccountHash(Hash::default()); accounts.len()];
+    // preimages of the accounts that fit in one chunk, back to back
+    let mut buffer = vec![];
+    let mut lanes = vec![];
+    for (index, (pubkey, account)) in accounts.iter().enumerate() {
+        if account.lamports() == 0 {
+            continue;
+        }
+        if ACCOUNT_PREIMAGE_FIELDS_LEN + account.data().len() > crate::blake3::CHUNK_LEN {
+            hashes[index] = hash_account(*account, pubkey);
+            continue;
+        }
+        let start = buffer.len();
+        buffer.extend_from_slice(&account.lamports().to_le_bytes());
+        buffer.extend_from_slice(&account.rent_epoch().to_le_bytes());
+        buffer.extend_from_slice(account.data());
+        buffer.push(account.executable() as u8);
+        buffer.extend_from_slice(account.owner().as_ref());
+        buffer.extend_from_slice(pubkey.as_ref());
+        lanes.push((index, start..buffer.len()));
+    }
+
+    let preimages: Vec<&[u8]> = lanes
+        .iter()
+        .map(|(_, range)| &buffer[range.clone()])
+        .collect();
+    for ((index, _), hash) in lanes.iter().zip(crate::blake3::hash_many(&preimages)) {
+        hashes[*index] = AccountHash(hash);
+    }
+    hashes
+}
+
+#[derive(Debug)]
+struct CachedAccountHash {
+    lamports: u64,
+    owner: Pubkey,
+    executable: bool,
+    rent_epoch: Epoch,
+    /// The data buffer that was hashed. Only its address is compared; the
+    /// weak reference keeps that address from being reused without keeping
+    /// the data alive.
+    data: Weak<Vec<u8>>,
+    hash: AccountHash,
+}
+
+impl CachedAccountHash {
+    fn matches(&self, account: &AccountSharedData) -> bool {
+        self.lamports == account.lamports
+            && self.owner == account.owner
+            && self.executable == account.executable
+            && self.rent_epoch == account.rent_epoch
+            && Weak::as_ptr(&self.data) == Arc::as_ptr(&account.data)
+    }
+}
+
+/// Account hashes kept across slots, so that accounts which did not change
+/// are not hashed again.
+///
+/// An account is unchanged if its fields are equal and its data is still the
+/// buffer it had when it was hashed, which takes constant time to check.
+///
+/// The cache only holds weak references to the data, so it neither keeps
+/// dropped data alive nor makes writes copy it. While an account is cached, the
+/// first write through a `&mut` accessor moves its data into a new shared
+/// allocation without copying the bytes, and
+/// [`AccountSharedData::set_data_from_slice`] writes to a new buffer instead of
+/// reusing the old one. Each entry keeps a small allocation alive until it is
+/// replaced or removed.
+#[derive(Debug, Default)]
+pub struct AccountHashCache {
+    entries: HashMap<Pubkey, CachedAccountHash>,
+}
+
+impl AccountHashCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hash a batch of accounts as [`hash_accounts`] does, only hashing the
+    /// accounts that changed since they were last hashed through this cache.
+    pub fn hash_accounts(&mut self, accounts: &[(Pubkey, &AccountSharedData)]) -> Vec<AccountHash> {
+        let mut hashes = Vec::with_capacity(accounts.len());
+        let mut misses = vec![];
+        for (index, (pubkey, account)) in accounts.iter().enumerate() {
+            match self.entries.get(pubkey) {
+                Some(entry) if entry.matches(account) => hashes.push(entry.hash),
+                _ => {
+                    hashes.push(AccountHash(Hash::default()));
+                    misses.push(index);
+                }
+            }
+        }
+
+        let missed: Vec<_> = misses.iter().map(|&index| accounts[index]).collect();
+        for (index, hash) in misses.into_iter().zip(hash_accounts(&missed)) {
+            let (pubkey, account) = accounts[index];
+            hashes[index] = hash;
+            self.entries.insert(
+                pubkey,
+                CachedAccountHash {
+                    lamports: account.lamports,
+                    owner: account.owner,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    data: Arc::downgrade(&account.data),
+                    hash,
+                },
+            );
+        }
+        hashes
+    }
+
+    /// Forget the hash of the account at `pubkey`.
+    pub fn remove(&mut self, pubkey: &Pubkey) {
+        self.entries.remove(pubkey);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Lattice hash of an account, to be mixed into an accounts [`LtHash`].
+///
+/// Accounts with zero lamports do not exist, so they hash to the identity.
+pub fn hash_account_lt<T: ReadableAccount>(account: &T, pubkey: &Pubkey) -> LtHash {
+    if account.lamports() == 0 {
+        return LtHash::identity();
+    }
+    LtHash::hashv(&[
+        &account.lamports().to_le_bytes(),
         account.data(),
-        pubkey,
-    )
+        &[account.executable().into()],
+        account.owner().as_ref(),
+        pubkey.as_ref(),
+    ])
 }
 
-fn hash_account_data(
+fn hash_account_data<H: HashFunction>(
     lamports: u64,
-    owner: &Pubkey,
+    slot: Option<Slot>,
+    owner: Option<&Pubkey>,
     executable: bool,
     rent_epoch: Epoch,
     data: &[u8],
@@ -28,7 +272,7 @@ fn hash_account_data(
     if lamports == 0 {
         return AccountHash(Hash::default());
     }
-    let mut hasher = blake3::Hasher::new();
+    let mut hasher = GenericHasher::<H>::default();
 
     // allocate 128 bytes buffer on the stack
     const BUF_SIZE: usize = 128;
@@ -40,15 +284,19 @@ fn hash_account_data(
     // collect lamports, slot, rent_epoch into buffer to hash
     buffer.extend_from_slice(&lamports.to_le_bytes());
 
+    if let Some(slot) = slot {
+        buffer.extend_from_slice(&slot.to_le_bytes());
+    }
+
     buffer.extend_from_slice(&rent_epoch.to_le_bytes());
 
     if data.len() > DATA_SIZE_CAN_FIT {
         // For larger accounts whose data can't fit into the buffer, update the hash now.
-        hasher.update(&buffer);
+        hasher.hash(&buffer);
         buffer.clear();
 
         // hash account's data
-        hasher.update(data);
+        hasher.hash(data);
     } else {
         // For small accounts whose data can fit into the buffer, append it to the buffer.
         buffer.extend_from_slice(data);
@@ -60,9 +308,538 @@ fn hash_account_data(
     } else {
         buffer.push(0_u8);
     }
-    buffer.extend_from_slice(owner.as_ref());
+    if let Some(owner) = owner {
+        buffer.extend_from_slice(owner.as_ref());
+    }
     buffer.extend_from_slice(pubkey.as_ref());
-    hasher.update(&buffer);
+    hasher.hash(&buffer);
+
+    AccountHash(hasher.result())
+}
+
+/// Slots whose writes are visible to a read, in addition to the rooted slots
+/// that are not newer than the newest rooted one among them.
+pub type Ancestors = HashSet<Slot>;
+
+#[derive(Debug, Default)]
+struct AccountsDbInner {
+    /// Versions of each account, ordered by slot, at most one per slot.
+    index: HashMap<Pubkey, Vec<(Slot, AccountSharedData)>>,
+    /// Accounts stored in each slot that was not cleaned up yet.
+    slots: HashMap<Slot, HashSet<Pubkey>>,
+    roots: BTreeSet<Slot>,
+}
+
+/// An in-memory accounts database that keeps the version of every account
+/// stored in each slot, so that reads see the state of a given fork.
+///
+/// A read sees its ancestors, and the rooted slots up to the newest rooted
+/// slot among its ancestors, so a fork never sees the roots of a sibling fork.
+/// A read with no ancestors sees every rooted slot. Once a slot is rooted,
+/// older rooted versions of the accounts stored in it are dropped, so reads are
+/// only accurate on forks that descend from the newest root.
+#[derive(Debug, Default)]
+pub struct AccountsDb {
+    inner: RwLock<AccountsDbInner>,
+}
+
+impl AccountsDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `accounts` in `slot`, replacing the versions previously stored in
+    /// that slot.
+    pub fn store(&self, slot: Slot, accounts: &[(Pubkey, AccountSharedData)]) {
+        let mut inner = self.inner.write();
+        for (pubkey, account) in accounts {
+            let versions = inner.index.entry(*pubkey).or_default();
+            match versions.binary_search_by_key(&slot, |(slot, _)| *slot) {
+                Ok(index) => versions[index].1 = account.clone(),
+                Err(index) => versions.insert(index, (slot, account.clone())),
+            }
+            inner.slots.entry(slot).or_default().insert(*pubkey);
+        }
+    }
 
-    AccountHash(Hash::new_from_array(hasher.finalize().into()))
+    /// Load the newest version of the account at `pubkey` that was stored in
+    /// one of `ancestors` or in a rooted slot no newer than the newest rooted
+    /// slot of `ancestors`, and the slot it was stored in. With no ancestors,
+    /// every rooted slot is visible.
+    ///
+    /// An account whose newest version has zero lamports does not exist, so
+    /// `None` is returned for it whether or not [`add_root`] dropped it yet.
+    ///
+    /// [`add_root`]: AccountsDb::add_root
+    pub fn load(
+        &self,
+        ancestors: &Ancestors,
+        pubkey: &Pubkey,
+    ) -> Option<(AccountSharedData, Slot)> {
+        let inner = self.inner.read();
+        let newest_root = if ancestors.is_empty() {
+            inner.roots.last().copied()
+        } else {
+            ancestors
+                .iter()
+                .filter(|slot| inner.roots.contains(slot))
+                .max()
+                .copied()
+        };
+        inner
+            .index
+            .get(pubkey)?
+            .iter()
+            .rev()
+            .find(|(slot, _)| {
+                ancestors.contains(slot)
+                    || (inner.roots.contains(slot)
+                        && newest_root.is_some_and(|newest| *slot <= newest))
+            })
+            .filter(|(_, account)| account.lamports() != 0)
+            .map(|(slot, account)| (account.clone(), *slot))
+    }
+
+    pub fn is_root(&self, slot: Slot) -> bool {
+        self.inner.read().roots.contains(&slot)
+    }
+
+    /// Mark `slot` as rooted, and drop the versions its accounts had in older
+    /// rooted slots.
+    ///
+    /// If the newest rooted version of an account has zero lamports and is
+    /// its only version left, the account is dropped altogether.
+    pub fn add_root(&self, slot: Slot) {
+        let mut inner = self.inner.write();
+        let inner = &mut *inner;
+        inner.roots.insert(slot);
+        let Some(pubkeys) = inner.slots.remove(&slot) else {
+            return;
+        };
+
+        for pubkey in pubkeys {
+            let Some(versions) = inner.index.get_mut(&pubkey) else {
+                continue;
+            };
+            let Some(newest_root) = versions
+                .iter()
+                .rposition(|(slot, _)| inner.roots.contains(slot))
+            else {
+                continue;
+            };
+            let mut index = 0;
+            versions.retain(|(slot, _)| {
+                let keep = index >= newest_root || !inner.roots.contains(slot);
+                index += 1;
+                keep
+            });
+            if let [(_, account)] = versions.as_slice() {
+                if account.lamports() == 0 && inner.roots.contains(&versions[0].0) {
+                    inner.index.remove(&pubkey);
+                }
+            }
+        }
+    }
+
+    /// Drop all accounts stored in `slot`, for a slot on a fork that was
+    /// abandoned. Rooted slots are never purged.
+    pub fn purge_slot(&self, slot: Slot) {
+        let mut inner = self.inner.write();
+        if inner.roots.contains(&slot) {
+            log::warn!("not purging rooted slot {}", slot);
+            return;
+        }
+        let Some(pubkeys) = inner.slots.remove(&slot) else {
+            return;
+        };
+        for pubkey in pubkeys {
+            if let Some(versions) = inner.index.get_mut(&pubkey) {
+                versions.retain(|(version_slot, _)| *version_slot != slot);
+                if versions.is_empty() {
+                    inner.index.remove(&pubkey);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, WritableAccount};
+
+    /// The bytes hashed for an account, laid out one field after the other.
+    fn preimage(
+        account: &Account,
+        pubkey: &Pubkey,
+        slot: Option<Slot>,
+        include_owner: bool,
+    ) -> Vec<u8> {
+        let mut preimage = account.lamports.to_le_bytes().to_vec();
+        if let Some(slot) = slot {
+            preimage.extend_from_slice(&slot.to_le_bytes());
+        }
+        preimage.extend_from_slice(&account.rent_epoch.to_le_bytes());
+        preimage.extend_from_slice(&account.data);
+        preimage.push(account.executable as u8);
+        if include_owner {
+            preimage.extend_from_slice(account.owner.as_ref());
+        }
+        preimage.extend_from_slice(pubkey.as_ref());
+        preimage
+    }
+
+    fn sha256(preimage: &[u8]) -> Hash {
+        Hash::new_from_array(<sha2::Sha256 as sha2::Digest>::digest(preimage).into())
+    }
+
+    fn blake3(preimage: &[u8]) -> Hash {
+        Hash::new_from_array(*::blake3::hash(preimage).as_bytes())
+    }
+
+    #[test]
+    fn test_hash_account_with_version() {
+        let pubkey = Pubkey::new_unique();
+        let slot = 123_456;
+        let all_versions = [
+            AccountHashVersion::Sha256WithSlotWithoutOwner,
+            AccountHashVersion::Sha256WithSlot,
+            AccountHashVersion::Blake3WithSlotWithoutOwner,
+            AccountHashVersion::Blake3WithSlot,
+            AccountHashVersion::Blake3,
+        ];
+        // small enough to be buffered, and large enough to be hashed separately
+        for space in [0, 10, 60, 61, 1000] {
+            let mut account = Account::new(42, space, &Pubkey::new_unique());
+            account.data.fill(7);
+            account.executable = space % 2 == 0;
+            account.rent_epoch = 5;
+
+            let hash = |version| hash_account_with_version(&account, &pubkey, slot, version).0;
+            let expected = [
+                sha256(&preimage(&account, &pubkey, Some(slot), false)),
+                sha256(&preimage(&account, &pubkey, Some(slot), true)),
+                blake3(&preimage(&account, &pubkey, Some(slot), false)),
+                blake3(&preimage(&account, &pubkey, Some(slot), true)),
+                blake3(&preimage(&account, &pubkey, None, true)),
+            ];
+            for (version, expected) in all_versions.into_iter().zip(expected) {
+                assert_eq!(hash(version), expected, "{version:?} space {space}");
+            }
+            assert_eq!(
+                hash_account(&account, &pubkey).0,
+                hash(AccountHashVersion::Blake3)
+            );
+            assert_eq!(
+                hash_account_with_version(&account, &pubkey, slot + 1, AccountHashVersion::Blake3),
+                hash_account(&account, &pubkey)
+            );
+            assert_ne!(
+                hash_account_with_version(
+                    &account,
+                    &pubkey,
+                    slot + 1,
+                    AccountHashVersion::Blake3WithSlot
+                )
+                .0,
+                hash(AccountHashVersion::Blake3WithSlot)
+            );
+
+            account.lamports = 0;
+            for version in all_versions {
+                assert_eq!(
+                    hash_account_with_version(&account, &pubkey, slot, version).0,
+                    Hash::default()
+                );
+            }
+        }
+    }
+
+    fn random_accounts(count: usize) -> Vec<(Pubkey, AccountSharedData)> {
+        (0..count)
+            .map(|i| {
+                let mut account = AccountSharedData::new(i as u64, i % 300, &Pubkey::new_unique());
+                account.data_as_mut_slice().fill(i as u8);
+                (Pubkey::new_unique(), account)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hash_accounts() {
+        for count in [0, 1, 10, 1000] {
+            let accounts = random_accounts(count);
+            let batch: Vec<_> = accounts.iter().map(|(k, a)| (*k, a)).collect();
+            let expected: Vec<_> = accounts.iter().map(|(k, a)| hash_account(a, k)).collect();
+            assert_eq!(hash_accounts(&batch), expected);
+        }
+
+        // around the largest account hashed in a lane, and mixed with zero
+        // lamport and larger accounts within the same lanes
+        let max_lane_data_len = crate::blake3::CHUNK_LEN - ACCOUNT_PREIMAGE_FIELDS_LEN;
+        let accounts: Vec<_> = (0..200)
+            .map(|i| {
+                let space = match i % 5 {
+                    0 => max_lane_data_len - 1,
+                    1 => max_lane_data_len,
+                    2 => max_lane_data_len + 1,
+                    3 => 5000,
+                    _ => i,
+                };
+                let mut account =
+                    AccountSharedData::new((i % 7) as u64, space, &Pubkey::new_unique());
+                account.data_as_mut_slice().fill(i as u8);
+                account.set_executable(i % 2 == 0);
+                account.set_rent_epoch(i as u64);
+                (Pubkey::new_unique(), account)
+            })
+            .collect();
+        let batch: Vec<_> = accounts.iter().map(|(k, a)| (*k, a)).collect();
+        let expected: Vec<_> = accounts.iter().map(|(k, a)| hash_account(a, k)).collect();
+        assert_eq!(hash_accounts(&batch), expected);
+        assert_eq!(hash_accounts_in_lanes(&batch), expected);
+    }
+
+    #[test]
+    fn test_account_hash_cache() {
+        let mut accounts = random_accounts(200);
+        let mut cache = AccountHashCache::new();
+        let expected = |accounts: &[(Pubkey, AccountSharedData)]| -> Vec<_> {
+            accounts.iter().map(|(k, a)| hash_account(a, k)).collect()
+        };
+
+        for slot in 0..4 {
+            let batch: Vec<_> = accounts.iter().map(|(k, a)| (*k, a)).collect();
+            assert_eq!(cache.hash_accounts(&batch), expected(&accounts));
+            assert_eq!(cache.len(), accounts.len());
+            assert!(accounts.iter().all(|(_, account)| !account.is_shared()));
+
+            // change some of the accounts through every kind of write
+            for (index, (_, account)) in accounts.iter_mut().enumerate().skip(slot) {
+                match index % 7 {
+                    0 => account.data_as_mut_slice().fill(slot as u8 + 1),
+                    1 => account.set_data_from_slice(&[slot as u8; 3]),
+                    2 => account.extend_from_slice(&[1]),
+                    3 => account.resize(account.data().len() + 1, 0),
+                    4 => account.checked_add_lamports(1).unwrap(),
+                    5 => account.set_executable(!account.executable()),
+                    _ => {}
+                }
+            }
+        }
+
+        // writing in place does not copy the data of a cached account
+        let (pubkey, account) = &mut accounts[1];
+        cache.hash_accounts(&[(*pubkey, &*account)]);
+        let data = account.data().as_ptr();
+        account.data_as_mut_slice()[0] ^= 1;
+        assert_eq!(account.data().as_ptr(), data);
+        assert_eq!(
+            cache.hash_accounts(&[(*pubkey, &*account)]),
+            vec![hash_account(&*account, pubkey)]
+        );
+
+        // a clone shares the buffer with the cached account
+        let (pubkey, account) = accounts[6].clone();
+        assert_eq!(
+            cache.hash_accounts(&[(pubkey, &account)]),
+            vec![hash_account(&account, &pubkey)]
+        );
+
+        cache.remove(&pubkey);
+        assert_eq!(cache.len(), accounts.len() - 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_hash_account_lt() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut account = Account::new(0, 10, &owner);
+        assert_eq!(hash_account_lt(&account, &pubkey), LtHash::identity());
+
+        account.lamports = 1;
+        let hash = hash_account_lt(&account, &pubkey);
+        assert_ne!(hash, LtHash::identity());
+        assert_ne!(hash, hash_account_lt(&account, &Pubkey::new_unique()));
+        assert_eq!(
+            hash,
+            LtHash::hashv(&[
+                &1u64.to_le_bytes(),
+                &[0; 10],
+                &[0],
+                owner.as_ref(),
+                pubkey.as_ref()
+            ])
+        );
+
+        // updating an account replaces its contribution to the accounts lattice hash
+        let other = (Pubkey::new_unique(), Account::new(5, 0, &owner));
+        let mut accounts_lt_hash = LtHash::identity();
+        accounts_lt_hash.mix_in(&hash);
+        accounts_lt_hash.mix_in(&hash_account_lt(&other.1, &other.0));
+
+        account.executable = true;
+        accounts_lt_hash.mix_out(&hash);
+        accounts_lt_hash.mix_in(&hash_account_lt(&account, &pubkey));
+
+        let mut expected = hash_account_lt(&other.1, &other.0);
+        expected.mix_in(&hash_account_lt(&account, &pubkey));
+        assert_eq!(accounts_lt_hash, expected);
+    }
+
+    #[test]
+    fn test_hash_account_lt_vector() {
+        let pubkey = Pubkey::from([1; 32]);
+        let mut account = Account::new(42, 10, &Pubkey::from([2; 32]));
+        account.data.fill(3);
+        account.executable = true;
+        let lt_hash = hash_account_lt(&account, &pubkey);
+
+        // the elements are the first 2048 bytes of the blake3 XOF of the
+        // preimage, read as little endian u16s
+        let mut preimage = 42u64.to_le_bytes().to_vec();
+        preimage.extend_from_slice(&[3; 10]);
+        preimage.push(1);
+        preimage.extend_from_slice(&[2; 32]);
+        preimage.extend_from_slice(&[1; 32]);
+        let mut xof = [0u8; 4096];
+        ::blake3::Hasher::new()
+            .update(&preimage)
+            .finalize_xof()
+            .fill(&mut xof);
+        let elements = &xof[..2 * LtHash::NUM_ELEMENTS];
+        assert_eq!(lt_hash.checksum(), crate::blake3::hash(elements));
+        // with 2048 elements, the checksum would cover 4096 bytes
+        assert_ne!(lt_hash.checksum(), crate::blake3::hash(&xof));
+
+        // regression vector produced by this implementation, pinning the
+        // preimage, the length and the XOF layout
+        assert_eq!(
+            lt_hash.checksum().to_string(),
+            "GndMKDbeCQFpJXZnNAztEot6pvTo5CXMgXgr7J7no4Xj"
+        );
+    }
+
+    fn account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &Pubkey::default())
+    }
+
+    #[test]
+    fn test_accounts_db_forks() {
+        let db = AccountsDb::new();
+        let pubkey = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        //   0
+        //  / \
+        // 1   2
+        // |
+        // 3
+        db.store(0, &[(pubkey, account(1)), (other, account(10))]);
+        db.store(1, &[(pubkey, account(2))]);
+        db.store(2, &[(pubkey, account(3))]);
+        db.store(3, &[(pubkey, account(4)), (other, account(0))]);
+
+        let ancestors = |slots: &[Slot]| slots.iter().copied().collect::<Ancestors>();
+        assert_eq!(db.load(&ancestors(&[]), &pubkey), None);
+        assert_eq!(db.load(&ancestors(&[0]), &pubkey), Some((account(1), 0)));
+        assert_eq!(db.load(&ancestors(&[0, 1]), &pubkey), Some((account(2), 1)));
+        assert_eq!(db.load(&ancestors(&[0, 2]), &pubkey), Some((account(3), 2)));
+        assert_eq!(
+            db.load(&ancestors(&[0, 1, 3]), &pubkey),
+            Some((account(4), 3))
+        );
+        // zero lamport accounts do not exist, before and after being cleaned up
+        assert_eq!(db.load(&ancestors(&[0, 1, 3]), &other), None);
+        assert_eq!(db.load(&ancestors(&[0, 1]), &other), Some((account(10), 0)));
+        assert_eq!(db.load(&ancestors(&[0, 1]), &Pubkey::new_unique()), None);
+
+        // storing again in a slot replaces the version of that slot
+        db.store(1, &[(pubkey, account(5))]);
+        assert_eq!(db.load(&ancestors(&[0, 1]), &pubkey), Some((account(5), 1)));
+
+        // a read without ancestors sees every root
+        db.add_root(0);
+        assert!(db.is_root(0));
+        assert_eq!(db.load(&ancestors(&[]), &pubkey), Some((account(1), 0)));
+        assert_eq!(db.load(&ancestors(&[2]), &pubkey), Some((account(3), 2)));
+
+        db.add_root(1);
+        db.purge_slot(2);
+        assert_eq!(db.load(&ancestors(&[]), &pubkey), Some((account(5), 1)));
+        assert_eq!(db.load(&ancestors(&[]), &other), Some((account(10), 0)));
+
+        // roots newer than a fork are not visible to it
+        let created = Pubkey::new_unique();
+        db.store(3, &[(created, account(6))]);
+        db.add_root(3);
+        assert_eq!(db.load(&ancestors(&[0, 1]), &created), None);
+        assert_eq!(db.load(&ancestors(&[]), &created), Some((account(6), 3)));
+        assert_eq!(
+            db.load(&ancestors(&[3, 4]), &created),
+            Some((account(6), 3))
+        );
+        assert_eq!(db.load(&ancestors(&[]), &pubkey), Some((account(4), 3)));
+        // the only version left has zero lamports
+        assert_eq!(db.load(&ancestors(&[]), &other), None);
+        assert_eq!(db.inner.read().index[&pubkey].len(), 1);
+        assert!(!db.inner.read().index.contains_key(&other));
+    }
+
+    #[test]
+    fn test_accounts_db_sibling_forks() {
+        // 0 - 1 - 3
+        //  \
+        //   2 - 4
+        let db = AccountsDb::new();
+        let pubkey = Pubkey::new_unique();
+        let created = Pubkey::new_unique();
+        db.store(0, &[(pubkey, account(1))]);
+        db.store(1, &[(pubkey, account(2)), (created, account(7))]);
+        db.store(2, &[(pubkey, account(3))]);
+        db.add_root(0);
+        db.add_root(1);
+
+        let ancestors = |slots: &[Slot]| slots.iter().copied().collect::<Ancestors>();
+        // the rooted branch sees its root
+        assert_eq!(
+            db.load(&ancestors(&[0, 1, 3]), &pubkey),
+            Some((account(2), 1))
+        );
+        assert_eq!(
+            db.load(&ancestors(&[0, 1, 3]), &created),
+            Some((account(7), 1))
+        );
+        // the sibling branch does not, even though its slots are newer
+        assert_eq!(
+            db.load(&ancestors(&[0, 2, 4]), &pubkey),
+            Some((account(3), 2))
+        );
+        assert_eq!(db.load(&ancestors(&[0, 2, 4]), &created), None);
+        assert_eq!(db.load(&ancestors(&[2, 4]), &created), None);
+    }
+
+    #[test]
+    fn test_accounts_db_purge_slot() {
+        let db = AccountsDb::new();
+        let pubkey = Pubkey::new_unique();
+        db.store(0, &[(pubkey, account(1))]);
+        db.store(1, &[(pubkey, account(2))]);
+        db.add_root(0);
+
+        let ancestors: Ancestors = [0, 1].into_iter().collect();
+        db.purge_slot(1);
+        assert_eq!(db.load(&ancestors, &pubkey), Some((account(1), 0)));
+
+        // rooted slots are not purged
+        db.purge_slot(0);
+        assert_eq!(db.load(&ancestors, &pubkey), Some((account(1), 0)));
+
+        let new_pubkey = Pubkey::new_unique();
+        db.store(2, &[(new_pubkey, account(1))]);
+        db.purge_slot(2);
+        assert!(!db.inner.read().index.contains_key(&new_pubkey));
+    }
 }
@@ -0,0 +1,434 @@
+//! An accounts merkle tree that is kept up to date as accounts change.
+//!
+//! [`AccountsHasher::accumulate_account_hashes`] rebuilds the whole tree from
+//! scratch. [`AccountsMerkleTree`] keeps the leaves sorted by pubkey together
+//! with every inner node, and only rehashes the nodes that changed, while
+//! producing exactly the same root.
+//!
+//! [`AccountsHasher::accumulate_account_hashes`]: super::account_hasher::AccountsHasher::accumulate_account_hashes
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::account_hasher::{AccountHash, MERKLE_FANOUT};
+use super::merkle_proof::{MerkleProof, MerkleProofLevel};
+use crate::hash::{GenericHasher, Hash, HashFunction, Sha256};
+use crate::pubkey::Pubkey;
+
+/// Merkle tree over account hashes, keyed and ordered by pubkey.
+///
+/// Changes are applied to the leaves right away and to the inner nodes the
+/// next time the root or a proof is requested. Changing the hash of an
+/// existing account only rehashes its path to the root. Inserting or removing
+/// an account moves every later leaf, so all nodes to the right of it are
+/// rehashed as well.
+///
+/// Inner nodes are hashed with `H`, as in
+/// [`AccountsHasher::compute_merkle_root_with`].
+///
+/// [`AccountsHasher::compute_merkle_root_with`]: super::account_hasher::AccountsHasher::compute_merkle_root_with
+#[derive(Clone, Default)]
+pub struct GenericAccountsMerkleTree<H: HashFunction> {
+    leaves: Vec<(Pubkey, AccountHash)>,
+    /// `levels[k]` holds the nodes `k + 1` levels above the leaves; the last
+    /// level holds the root only.
+    levels: Vec<Vec<Hash>>,
+    /// Leaves whose hash changed in place since the inner nodes were updated.
+    dirty: BTreeSet<usize>,
+    /// First leaf whose position may have changed since the inner nodes were
+    /// updated.
+    shifted_from: Option<usize>,
+    hash_function: PhantomData<H>,
+}
+
+/// A [`GenericAccountsMerkleTree`] hashing with SHA-256, like
+/// [`AccountsHasher::accumulate_account_hashes`].
+///
+/// [`AccountsHasher::accumulate_account_hashes`]: super::account_hasher::AccountsHasher::accumulate_account_hashes
+pub type AccountsMerkleTree = GenericAccountsMerkleTree<Sha256>;
+
+impl<H: HashFunction> GenericAccountsMerkleTree<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&AccountHash> {
+        self.position(pubkey)
+            .ok()
+            .map(|index| &self.leaves[index].1)
+    }
+
+    /// Insert or update the hash of the account at `pubkey`, returning its
+    /// previous hash.
+    ///
+    /// Updating an existing account costs `O(log n)` at the next [`root`].
+    /// Inserting a new account is `O(n)`: it shifts every later leaf, and the
+    /// next [`root`] rehashes every node to the right of it. The root is
+    /// defined by the position of each leaf, so no layout that keeps it equal
+    /// to [`accumulate_account_hashes`] can avoid this. Inserts and removals
+    /// between two calls to [`root`] are rehashed together, from the leftmost
+    /// one.
+    ///
+    /// [`root`]: GenericAccountsMerkleTree::root
+    /// [`accumulate_account_hashes`]: super::account_hasher::AccountsHasher::accumulate_account_hashes
+    pub fn insert(&mut self, pubkey: Pubkey, hash: AccountHash) -> Option<AccountHash> {
+        match self.position(&pubkey) {
+            Ok(index) => {
+                let old = std::mem::replace(&mut self.leaves[index].1, hash);
+                if old != hash {
+                    self.dirty.insert(index);
+                }
+                Some(old)
+            }
+            Err(index) => {
+                self.leaves.insert(index, (pubkey, hash));
+                self.mark_shifted(index);
+                None
+            }
+        }
+    }
+
+    /// Remove the account at `pubkey`, returning its hash.
+    ///
+    /// Like inserting a new account, this is `O(n)`: every later leaf moves,
+    /// and the next [`root`] rehashes every node to the right of it.
+    ///
+    /// [`root`]: GenericAccountsMerkleTree::root
+    pub fn remove(&mut self, pubkey: &Pubkey) -> Option<AccountHash> {
+        let index = self.position(pubkey).ok()?;
+        let (_, hash) = self.leaves.remove(index);
+        self.mark_shifted(index);
+        Some(hash)
+    }
+
+    /// The root of the tree, equal to `compute_merkle_root_with::<H>` over all
+    /// of its accounts sorted by pubkey, which for SHA-256 is
+    /// `accumulate_account_hashes`.
+    pub fn root(&mut self) -> Hash {
+        self.update_levels();
+        self.levels
+            .last()
+            .map_or_else(|| GenericHasher::<H>::default().result(), |level| level[0])
+    }
+
+    /// Generate a proof that the account hash of `pubkey` is part of the tree,
+    /// as [`AccountsHasher::generate_account_proof`] does.
+    ///
+    /// Returns `None` if `pubkey` is not in the tree.
+    ///
+    /// [`AccountsHasher::generate_account_proof`]: super::account_hasher::AccountsHasher::generate_account_proof
+    pub fn proof(&mut self, pubkey: &Pubkey) -> Option<MerkleProof> {
+        let mut index = self.position(pubkey).ok()?;
+        self.update_levels();
+
+        let levels = (0..self.levels.len())
+            .map(|depth| {
+                let start = index / MERKLE_FANOUT * MERKLE_FANOUT;
+                let end = std::cmp::min(start + MERKLE_FANOUT, self.level_len(depth));
+                let siblings = (start..end)
+                    .filter(|&position| position != index)
+                    .map(|position| self.node(depth, position))
+                    .collect();
+                let level = MerkleProofLevel {
+                    index: index - start,
+                    siblings,
+                };
+                index /= MERKLE_FANOUT;
+                level
+            })
+            .collect();
+        Some(MerkleProof { levels })
+    }
+
+    fn position(&self, pubkey: &Pubkey) -> Result<usize, usize> {
+        self.leaves.binary_search_by_key(pubkey, |a| a.0)
+    }
+
+    fn mark_shifted(&mut self, index: usize) {
+        self.shifted_from = Some(self.shifted_from.map_or(index, |from| from.min(index)));
+    }
+
+    /// Number of nodes `depth` levels above the leaves.
+    fn level_len(&self, depth: usize) -> usize {
+        if depth == 0 {
+            self.leaves.len()
+        } else {
+            self.levels[depth - 1].len()
+        }
+    }
+
+    /// Node at `index`, `depth` levels above the leaves.
+    fn node(&self, depth: usize, index: usize) -> Hash {
+        if depth == 0 {
+            self.leaves[index].1 .0
+        } else {
+            self.levels[depth - 1][index]
+        }
+    }
+
+    /// Hash the children of the node at `index`, `depth + 1` levels above the
+    /// leaves.
+    fn hash_children(&self, depth: usize, index: usize) -> Hash {
+        let start = index * MERKLE_FANOUT;
+        let end = std::cmp::min(start + MERKLE_FANOUT, self.level_len(depth));
+        let mut hasher = GenericHasher::<H>::default();
+        for position in start..end {
+            hasher.hash(self.node(depth, position).as_ref());
+        }
+        hasher.result()
+    }
+
+    /// Bring the inner nodes up to date with the leaves, returning the number
+    /// of nodes that were rehashed.
+    fn update_levels(&mut self) -> usize {
+        if self.dirty.is_empty() && self.shifted_from.is_none() {
+            return 0;
+        }
+        let mut dirty = std::mem::take(&mut self.dirty);
+        let mut shifted_from = self.shifted_from.take();
+        if self.leaves.is_empty() {
+            self.levels.clear();
+            return 0;
+        }
+
+        let mut rehashed = 0;
+        let mut depth = 0;
+        loop {
+            let len = self.level_len(depth).div_ceil(MERKLE_FANOUT);
+            // a new level has to be computed in full
+            let first_shifted = if depth < self.levels.len() {
+                shifted_from.map_or(len, |from| from / MERKLE_FANOUT)
+            } else {
+                self.levels.push(vec![]);
+                0
+            };
+
+            let mut level = std::mem::take(&mut self.levels[depth]);
+            level.resize(len, Hash::default());
+            let dirty_parents: BTreeSet<usize> = dirty
+                .iter()
+                .map(|index| index / MERKLE_FANOUT)
+                .filter(|&index| index < first_shifted)
+                .collect();
+            for &index in &dirty_parents {
+                level[index] = self.hash_children(depth, index);
+            }
+            for (index, node) in level.iter_mut().enumerate().skip(first_shifted) {
+                *node = self.hash_children(depth, index);
+            }
+            rehashed += dirty_parents.len() + len.saturating_sub(first_shifted);
+            self.levels[depth] = level;
+
+            dirty = dirty_parents;
+            shifted_from = shifted_from.map(|_| first_shifted);
+            depth += 1;
+            if len == 1 {
+                self.levels.truncate(depth);
+                return rehashed;
+            }
+        }
+    }
+}
+
+impl<H: HashFunction> fmt::Debug for GenericAccountsMerkleTree<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GenericAccountsMerkleTree")
+            .field("leaves", &self.leaves)
+            .field("levels", &self.levels)
+            .field("dirty", &self.dirty)
+            .field("shifted_from", &self.shifted_from)
+            .finish()
+    }
+}
+
+impl<H: HashFunction> FromIterator<(Pubkey, AccountHash)> for GenericAccountsMerkleTree<H> {
+    /// Build a tree from account hashes. If a pubkey occurs more than once, its
+    /// last hash is kept.
+    fn from_iter<I: IntoIterator<Item = (Pubkey, AccountHash)>>(iter: I) -> Self {
+        let mut leaves: Vec<_> = iter.into_iter().collect();
+        leaves.reverse();
+        leaves.sort_by_key(|a| a.0);
+        leaves.dedup_by_key(|a| a.0);
+        Self {
+            leaves,
+            levels: vec![],
+            dirty: BTreeSet::new(),
+            shifted_from: Some(0),
+            hash_function: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::account::account_hasher::{AccountsHasher, MerkleTreeMode};
+    use crate::hash::Blake3;
+
+    fn expected_root(accounts: &HashMap<Pubkey, AccountHash>) -> Hash {
+        AccountsHasher::accumulate_account_hashes(
+            accounts
+                .iter()
+                .map(|(pubkey, hash)| (*pubkey, *hash))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let mut tree = AccountsMerkleTree::new();
+        assert!(tree.is_empty());
+        assert_eq!(
+            tree.root(),
+            AccountsHasher::accumulate_account_hashes(vec![])
+        );
+        assert!(tree.proof(&Pubkey::new_unique()).is_none());
+
+        let pubkey = Pubkey::new_unique();
+        tree.insert(pubkey, AccountHash(Hash::new_unique()));
+        tree.root();
+        tree.remove(&pubkey);
+        assert_eq!(
+            tree.root(),
+            AccountsHasher::accumulate_account_hashes(vec![])
+        );
+    }
+
+    #[test]
+    fn test_matches_accumulate_account_hashes() {
+        let mut tree = AccountsMerkleTree::new();
+        let mut expected = HashMap::new();
+        let mut pubkeys = vec![];
+        for round in 0..300 {
+            // mostly inserts early on, then a mix of updates and removals
+            for _ in 0..rand::random::<usize>() % 20 {
+                let hash = AccountHash(Hash::new_unique());
+                let op = rand::random::<u32>() % 4;
+                if pubkeys.is_empty() || (round < 100 && op != 0) || op == 1 {
+                    let pubkey = Pubkey::new_unique();
+                    pubkeys.push(pubkey);
+                    assert_eq!(tree.insert(pubkey, hash), None);
+                    expected.insert(pubkey, hash);
+                } else if op == 2 {
+                    let pubkey = pubkeys.swap_remove(rand::random::<usize>() % pubkeys.len());
+                    assert_eq!(tree.remove(&pubkey), expected.remove(&pubkey));
+                } else {
+                    let pubkey = pubkeys[rand::random::<usize>() % pubkeys.len()];
+                    assert_eq!(tree.insert(pubkey, hash), expected.insert(pubkey, hash));
+                }
+            }
+            assert_eq!(tree.len(), expected.len());
+            assert_eq!(tree.root(), expected_root(&expected), "round {round}");
+        }
+
+        let root = tree.root();
+        for (pubkey, hash) in &expected {
+            assert_eq!(tree.get(pubkey), Some(hash));
+            let proof = tree.proof(pubkey).unwrap();
+            assert!(AccountsHasher::verify_proof(&root, &hash.0, &proof));
+        }
+
+        let rebuilt: AccountsMerkleTree = expected.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(rebuilt.clone().root(), root);
+    }
+
+    #[test]
+    fn test_rehashed_nodes() {
+        // 6250, 391, 25, 2 and 1 inner nodes above the leaves
+        let mut tree: AccountsMerkleTree = (0..100_000)
+            .map(|_| (Pubkey::new_unique(), AccountHash(Hash::new_unique())))
+            .collect();
+        assert_eq!(tree.update_levels(), 6250 + 391 + 25 + 2 + 1);
+        assert_eq!(tree.update_levels(), 0);
+        let pubkeys: Vec<_> = tree.leaves.iter().map(|(pubkey, _)| *pubkey).collect();
+
+        // an update rehashes its path to the root only
+        tree.insert(pubkeys[50_000], AccountHash(Hash::new_unique()));
+        assert_eq!(tree.update_levels(), 5);
+
+        // updates sharing a path share its rehashed nodes
+        tree.insert(pubkeys[0], AccountHash(Hash::new_unique()));
+        tree.insert(pubkeys[1], AccountHash(Hash::new_unique()));
+        tree.insert(pubkeys[99_999], AccountHash(Hash::new_unique()));
+        assert_eq!(tree.update_levels(), 5 + 4);
+
+        // setting the same hash again changes nothing
+        let hash = *tree.get(&pubkeys[7]).unwrap();
+        tree.insert(pubkeys[7], hash);
+        assert_eq!(tree.update_levels(), 0);
+
+        // inserting or removing rehashes everything to its right
+        tree.remove(&pubkeys[50_000]);
+        assert_eq!(tree.update_levels(), 3125 + 196 + 13 + 2 + 1);
+        tree.insert(Pubkey::default(), AccountHash(Hash::new_unique()));
+        assert_eq!(tree.update_levels(), 6250 + 391 + 25 + 2 + 1);
+
+        // removing the last leaf shortens the last node of every level
+        tree.remove(&pubkeys[99_999]);
+        assert_eq!(tree.update_levels(), 1 + 1 + 1 + 1 + 1);
+        assert_eq!(
+            tree.root(),
+            AccountsHasher::accumulate_account_hashes(tree.leaves.clone())
+        );
+    }
+
+    #[test]
+    fn test_hash_function() {
+        let mut tree = GenericAccountsMerkleTree::<Blake3>::new();
+        assert_eq!(
+            tree.root(),
+            AccountsHasher::compute_merkle_root_with::<Blake3>(vec![], MERKLE_FANOUT)
+        );
+        let mut accounts: Vec<_> = (0..300)
+            .map(|_| (Pubkey::new_unique(), AccountHash(Hash::new_unique())))
+            .collect();
+        for (pubkey, hash) in &accounts {
+            tree.insert(*pubkey, *hash);
+        }
+        tree.remove(&accounts.pop().unwrap().0);
+        accounts.sort_unstable_by_key(|a| a.0);
+
+        let root = tree.root();
+        assert_eq!(
+            root,
+            AccountsHasher::compute_merkle_root_with::<Blake3>(
+                accounts.iter().map(|(k, v)| (*k, v.0)).collect(),
+                MERKLE_FANOUT
+            )
+        );
+        assert_ne!(
+            root,
+            AccountsHasher::accumulate_account_hashes(accounts.clone())
+        );
+        for (pubkey, hash) in &accounts {
+            let proof = tree.proof(pubkey).unwrap();
+            assert!(AccountsHasher::verify_proof_with::<Blake3>(
+                &root,
+                &hash.0,
+                &proof,
+                MerkleTreeMode::Legacy
+            ));
+        }
+    }
+
+    #[test]
+    fn test_from_iter_keeps_last_hash() {
+        let pubkey = Pubkey::new_unique();
+        let first = AccountHash(Hash::new_unique());
+        let last = AccountHash(Hash::new_unique());
+        let tree: AccountsMerkleTree = [(pubkey, first), (pubkey, last)].into_iter().collect();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.get(&pubkey), Some(&last));
+    }
+}
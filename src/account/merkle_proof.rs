@@ -0,0 +1,1358 @@
+//! Inclusion proofs for the accounts merkle tree built by [`AccountsHasher`].
+//!
+//! The tree hashes each run of up to `fanout` consecutive nodes into a parent
+//! node until a single root remains. A proof therefore records, for every
+//! level, the other members of the run the proven node was hashed with and the
+//! node's position among them.
+//!
+//! Since [`AccountsHasher::accumulate_account_hashes`] sorts leaves by pubkey,
+//! proving the two adjacent leaves around a pubkey also proves that the pubkey
+//! has no leaf of its own, see [`ExclusionProof`].
+//!
+//! Proofs for several leaves of the same tree share most of their upper
+//! levels; [`MerkleMultiProof`] carries each needed node only once.
+
+use serde::{Deserialize, Serialize};
+
+use super::account_hasher::{AccountHash, AccountsHasher, MerkleTreeMode, MERKLE_FANOUT};
+use super::accounts_db::hash_account;
+use super::{Account, ReadableAccount};
+use crate::hash::{Hash, HashFunction, Sha256};
+use crate::pubkey::Pubkey;
+
+/// One level of a [`MerkleProof`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProofLevel {
+    /// Position of the proven node within the run of nodes hashed together.
+    pub index: usize,
+    /// The other nodes of the run, in order, excluding the proven node.
+    pub siblings: Vec<Hash>,
+}
+
+/// Proof that a leaf is part of a tree with a given root, from the leaf level
+/// up to the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub levels: Vec<MerkleProofLevel>,
+}
+
+impl MerkleProof {
+    /// Compute the root of the tree that `leaf` is part of according to this proof.
+    ///
+    /// Returns `None` if the proof is malformed.
+    pub fn compute_root(&self, leaf: &Hash) -> Option<Hash> {
+        self.compute_root_with_mode(leaf, MerkleTreeMode::Legacy)
+    }
+
+    /// Same as [`compute_root`], for a tree hashed in the given `mode`.
+    ///
+    /// [`compute_root`]: MerkleProof::compute_root
+    pub fn compute_root_with_mode(&self, leaf: &Hash, mode: MerkleTreeMode) -> Option<Hash> {
+        self.compute_root_with::<Sha256>(leaf, mode)
+    }
+
+    /// Same as [`compute_root_with_mode`], hashing each node with `H` instead
+    /// of SHA-256.
+    ///
+    /// [`compute_root_with_mode`]: MerkleProof::compute_root_with_mode
+    pub fn compute_root_with<H: HashFunction>(
+        &self,
+        leaf: &Hash,
+        mode: MerkleTreeMode,
+    ) -> Option<Hash> {
+        if self.levels.is_empty() {
+            return None;
+        }
+        let mut node = mode.leaf_node::<H>(leaf);
+        for level in &self.levels {
+            if level.index > level.siblings.len() {
+                return None;
+            }
+            let (before, after) = level.siblings.split_at(level.index);
+            let mut hasher = mode.intermediate_hasher::<H>();
+            for sibling in before {
+                hasher.hash(sibling.as_ref());
+            }
+            hasher.hash(node.as_ref());
+            for sibling in after {
+                hasher.hash(sibling.as_ref());
+            }
+            node = hasher.result();
+        }
+        Some(node)
+    }
+
+    /// Position of the proven leaf in a tree built with `fanout`.
+    ///
+    /// Returns `None` if the proof could not have been generated with `fanout`.
+    pub fn leaf_index(&self, fanout: usize) -> Option<usize> {
+        let mut index = 0usize;
+        let mut stride = 1usize;
+        for level in &self.levels {
+            if level.siblings.len() >= fanout || level.index > level.siblings.len() {
+                return None;
+            }
+            index = index.checked_add(level.index.checked_mul(stride)?)?;
+            stride = stride.checked_mul(fanout)?;
+        }
+        Some(index)
+    }
+
+    /// Whether the proven leaf is the first leaf of the tree.
+    pub fn is_first(&self) -> bool {
+        self.levels.iter().all(|level| level.index == 0)
+    }
+
+    /// Whether the proven leaf is the last leaf of the tree.
+    pub fn is_last(&self) -> bool {
+        self.levels
+            .iter()
+            .all(|level| level.index == level.siblings.len())
+    }
+}
+
+/// A leaf adjacent to the range an [`ExclusionProof`] covers, revealed in full
+/// so the verifier can check which pubkey it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionProofNeighbor {
+    pub pubkey: Pubkey,
+    pub account: Account,
+    pub proof: MerkleProof,
+}
+
+/// Proof that a pubkey has no account with lamports in the tree built by
+/// [`AccountsHasher::accumulate_account_hashes`].
+///
+/// Leaves are sorted by pubkey, so it is enough to prove the closest accounts
+/// with lamports on either side of the pubkey, and that every leaf between
+/// them is the hash of a zero-lamport account. Zero-lamport accounts all hash
+/// to [`Hash::default`] regardless of their pubkey, so the pubkey may still be
+/// one of those leaves; either way it holds no lamports at that root.
+///
+/// `left` is `None` if the range starts at the first leaf, and `right` is
+/// `None` if it ends at the last leaf.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionProof {
+    pub left: Option<ExclusionProofNeighbor>,
+    pub gap: Vec<MerkleProof>,
+    pub right: Option<ExclusionProofNeighbor>,
+}
+
+/// Proof that several leaves are part of a tree with a given root.
+///
+/// `hashes` holds, level by level from the leaves up and in order within each
+/// level, every node that is needed to compute the root but cannot be computed
+/// from the proven leaves themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleMultiProof {
+    /// Number of leaves in the tree.
+    pub leaf_count: usize,
+    /// Positions of the proven leaves, strictly increasing.
+    pub indices: Vec<usize>,
+    pub hashes: Vec<Hash>,
+}
+
+impl MerkleMultiProof {
+    /// Compute the root of the tree built with `fanout` that `leaves` are part
+    /// of according to this proof. `leaves` are in the order of `indices`.
+    ///
+    /// Returns `None` if the proof is malformed.
+    pub fn compute_root(&self, leaves: &[Hash], fanout: usize) -> Option<Hash> {
+        self.compute_root_with_mode(leaves, fanout, MerkleTreeMode::Legacy)
+    }
+
+    /// Same as [`compute_root`], for a tree hashed in the given `mode`.
+    ///
+    /// [`compute_root`]: MerkleMultiProof::compute_root
+    pub fn compute_root_with_mode(
+        &self,
+        leaves: &[Hash],
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Option<Hash> {
+        self.compute_root_with::<Sha256>(leaves, fanout, mode)
+    }
+
+    /// Same as [`compute_root_with_mode`], hashing each node with `H` instead
+    /// of SHA-256.
+    ///
+    /// [`compute_root_with_mode`]: MerkleMultiProof::compute_root_with_mode
+    pub fn compute_root_with<H: HashFunction>(
+        &self,
+        leaves: &[Hash],
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Option<Hash> {
+        if fanout < 2
+            || leaves.is_empty()
+            || leaves.len() != self.indices.len()
+            || self.indices.windows(2).any(|pair| pair[0] >= pair[1])
+            || self.indices.last() >= Some(&self.leaf_count)
+        {
+            return None;
+        }
+
+        let mut known: Vec<(usize, Hash)> = self
+            .indices
+            .iter()
+            .copied()
+            .zip(leaves.iter().map(|leaf| mode.leaf_node::<H>(leaf)))
+            .collect();
+        let mut len = self.leaf_count;
+        let mut hashes = self.hashes.iter();
+        loop {
+            let mut parents = vec![];
+            let mut known_iter = known.into_iter().peekable();
+            while let Some(&(index, _)) = known_iter.peek() {
+                let start = index / fanout * fanout;
+                // `start + fanout` may overflow with a crafted `leaf_count`
+                let end = start + std::cmp::min(fanout, len - start);
+                let mut hasher = mode.intermediate_hasher::<H>();
+                for position in start..end {
+                    let node = match known_iter.next_if(|(i, _)| *i == position) {
+                        Some((_, node)) => node,
+                        None => *hashes.next()?,
+                    };
+                    hasher.hash(node.as_ref());
+                }
+                parents.push((index / fanout, hasher.result()));
+            }
+            known = parents;
+            len = AccountsHasher::div_ceil(len, fanout);
+            if len == 1 {
+                break;
+            }
+        }
+
+        if hashes.next().is_some() {
+            return None;
+        }
+        Some(known[0].1)
+    }
+}
+
+fn hash_chunk<H: HashFunction>(mode: MerkleTreeMode, chunk: &[Hash]) -> Hash {
+    let mut hasher = mode.intermediate_hasher::<H>();
+    for hash in chunk {
+        hasher.hash(hash.as_ref());
+    }
+    hasher.result()
+}
+
+impl AccountsHasher {
+    /// Generate a proof that `hashes[index]` is part of the tree whose root is
+    /// `compute_merkle_root_recurse(hashes, fanout)`.
+    ///
+    /// Returns `None` if `index` is out of bounds or `fanout` is less than 2.
+    pub fn generate_proof(hashes: &[Hash], index: usize, fanout: usize) -> Option<MerkleProof> {
+        Self::generate_proof_with_mode(hashes, index, fanout, MerkleTreeMode::Legacy)
+    }
+
+    /// Same as [`generate_proof`], for the tree whose root is
+    /// `compute_merkle_root_with_mode` over `hashes` in the given `mode`.
+    ///
+    /// Siblings on the lowest level are tree nodes, so in
+    /// [`MerkleTreeMode::DomainSeparated`] they are the prefixed hashes of the
+    /// other leaves.
+    ///
+    /// [`generate_proof`]: AccountsHasher::generate_proof
+    pub fn generate_proof_with_mode(
+        hashes: &[Hash],
+        index: usize,
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Option<MerkleProof> {
+        Self::generate_proof_with::<Sha256>(hashes, index, fanout, mode)
+    }
+
+    /// Same as [`generate_proof_with_mode`], hashing each node with `H` instead
+    /// of SHA-256.
+    ///
+    /// [`generate_proof_with_mode`]: AccountsHasher::generate_proof_with_mode
+    pub fn generate_proof_with<H: HashFunction>(
+        hashes: &[Hash],
+        index: usize,
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Option<MerkleProof> {
+        if fanout < 2 || index >= hashes.len() {
+            return None;
+        }
+
+        let mut levels = vec![];
+        let mut index = index;
+        let mut next_level: Vec<Hash>;
+        let mut level = hashes;
+        if mode != MerkleTreeMode::Legacy {
+            next_level = hashes.iter().map(|h| mode.leaf_node::<H>(h)).collect();
+            level = &next_level;
+        }
+        loop {
+            let start = index / fanout * fanout;
+            let end = std::cmp::min(start + fanout, level.len());
+            let mut siblings = level[start..end].to_vec();
+            siblings.remove(index - start);
+            levels.push(MerkleProofLevel {
+                index: index - start,
+                siblings,
+            });
+
+            next_level = level
+                .chunks(fanout)
+                .map(|chunk| hash_chunk::<H>(mode, chunk))
+                .collect();
+            if next_level.len() == 1 {
+                break;
+            }
+            level = &next_level;
+            index /= fanout;
+        }
+
+        Some(MerkleProof { levels })
+    }
+
+    /// Generate a proof that the leaves of `hashes` at `indices` are part of
+    /// the tree whose root is `compute_merkle_root_recurse(hashes, fanout)`.
+    ///
+    /// Returns `None` if `indices` is empty, any index is out of bounds or
+    /// `fanout` is less than 2.
+    pub fn generate_multi_proof(
+        hashes: &[Hash],
+        indices: &[usize],
+        fanout: usize,
+    ) -> Option<MerkleMultiProof> {
+        Self::generate_multi_proof_with_mode(hashes, indices, fanout, MerkleTreeMode::Legacy)
+    }
+
+    /// Same as [`generate_multi_proof`], for the tree whose root is
+    /// `compute_merkle_root_with_mode` over `hashes` in the given `mode`.
+    ///
+    /// As in [`generate_proof_with_mode`], nodes on the lowest level are the
+    /// prefixed hashes of the leaves in [`MerkleTreeMode::DomainSeparated`].
+    ///
+    /// [`generate_multi_proof`]: AccountsHasher::generate_multi_proof
+    /// [`generate_proof_with_mode`]: AccountsHasher::generate_proof_with_mode
+    pub fn generate_multi_proof_with_mode(
+        hashes: &[Hash],
+        indices: &[usize],
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Option<MerkleMultiProof> {
+        Self::generate_multi_proof_with::<Sha256>(hashes, indices, fanout, mode)
+    }
+
+    /// Same as [`generate_multi_proof_with_mode`], hashing each node with `H`
+    /// instead of SHA-256.
+    ///
+    /// [`generate_multi_proof_with_mode`]: AccountsHasher::generate_multi_proof_with_mode
+    pub fn generate_multi_proof_with<H: HashFunction>(
+        hashes: &[Hash],
+        indices: &[usize],
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> Option<MerkleMultiProof> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if fanout < 2 || indices.last().is_none_or(|&index| index >= hashes.len()) {
+            return None;
+        }
+
+        let mut proof_hashes = vec![];
+        let mut known = indices.clone();
+        let mut next_level: Vec<Hash>;
+        let mut level = hashes;
+        if mode != MerkleTreeMode::Legacy {
+            next_level = hashes.iter().map(|h| mode.leaf_node::<H>(h)).collect();
+            level = &next_level;
+        }
+        loop {
+            let mut parents = vec![];
+            let mut known_iter = known.iter().peekable();
+            while let Some(&&index) = known_iter.peek() {
+                let start = index / fanout * fanout;
+                let end = std::cmp::min(start + fanout, level.len());
+                for (position, node) in level.iter().enumerate().take(end).skip(start) {
+                    if known_iter.next_if(|&&i| i == position).is_none() {
+                        proof_hashes.push(*node);
+                    }
+                }
+                parents.push(index / fanout);
+            }
+
+            next_level = level
+                .chunks(fanout)
+                .map(|chunk| hash_chunk::<H>(mode, chunk))
+                .collect();
+            if next_level.len() == 1 {
+                break;
+            }
+            level = &next_level;
+            known = parents;
+        }
+
+        Some(MerkleMultiProof {
+            leaf_count: hashes.len(),
+            indices,
+            hashes: proof_hashes,
+        })
+    }
+
+    /// Generate a proof that the account hashes of all `pubkeys` are part of
+    /// the tree whose root is `accumulate_account_hashes(hashes)`.
+    ///
+    /// The proven leaves are ordered by pubkey. Returns `None` if `pubkeys` is
+    /// empty or any of them is not in `hashes`.
+    pub fn generate_account_multi_proof(
+        mut hashes: Vec<(Pubkey, AccountHash)>,
+        pubkeys: &[Pubkey],
+    ) -> Option<MerkleMultiProof> {
+        hashes.sort_unstable_by_key(|a| a.0);
+        let indices = pubkeys
+            .iter()
+            .map(|pubkey| hashes.binary_search_by_key(pubkey, |a| a.0).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let hashes: Vec<Hash> = hashes.into_iter().map(|(_, hash)| hash.0).collect();
+        Self::generate_multi_proof(&hashes, &indices, MERKLE_FANOUT)
+    }
+
+    /// Verify that `leaves`, in the order of `proof.indices`, are part of the
+    /// tree built with `fanout` with root `root`.
+    pub fn verify_multi_proof(
+        root: &Hash,
+        leaves: &[Hash],
+        proof: &MerkleMultiProof,
+        fanout: usize,
+    ) -> bool {
+        Self::verify_multi_proof_with_mode(root, leaves, proof, fanout, MerkleTreeMode::Legacy)
+    }
+
+    /// Verify that `leaves`, in the order of `proof.indices`, are part of the
+    /// tree built with `fanout` and hashed in `mode` with root `root`.
+    pub fn verify_multi_proof_with_mode(
+        root: &Hash,
+        leaves: &[Hash],
+        proof: &MerkleMultiProof,
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> bool {
+        Self::verify_multi_proof_with::<Sha256>(root, leaves, proof, fanout, mode)
+    }
+
+    /// Same as [`verify_multi_proof_with_mode`], hashing each node with `H`
+    /// instead of SHA-256.
+    ///
+    /// [`verify_multi_proof_with_mode`]: AccountsHasher::verify_multi_proof_with_mode
+    pub fn verify_multi_proof_with<H: HashFunction>(
+        root: &Hash,
+        leaves: &[Hash],
+        proof: &MerkleMultiProof,
+        fanout: usize,
+        mode: MerkleTreeMode,
+    ) -> bool {
+        proof.compute_root_with::<H>(leaves, fanout, mode).as_ref() == Some(root)
+    }
+
+    /// Generate a proof that the account hash of `pubkey` is part of the tree
+    /// whose root is `accumulate_account_hashes(hashes)`.
+    ///
+    /// Returns `None` if `pubkey` is not in `hashes`.
+    pub fn generate_account_proof(
+        mut hashes: Vec<(Pubkey, AccountHash)>,
+        pubkey: &Pubkey,
+    ) -> Option<MerkleProof> {
+        hashes.sort_unstable_by_key(|a| a.0);
+        let index = hashes.binary_search_by_key(pubkey, |a| a.0).ok()?;
+        let hashes: Vec<Hash> = hashes.into_iter().map(|(_, hash)| hash.0).collect();
+        Self::generate_proof(&hashes, index, MERKLE_FANOUT)
+    }
+
+    /// Verify that `leaf` is part of the tree with root `root`.
+    pub fn verify_proof(root: &Hash, leaf: &Hash, proof: &MerkleProof) -> bool {
+        proof.compute_root(leaf).as_ref() == Some(root)
+    }
+
+    /// Verify that `leaf` is part of the tree hashed in `mode` with root
+    /// `root`.
+    pub fn verify_proof_with_mode(
+        root: &Hash,
+        leaf: &Hash,
+        proof: &MerkleProof,
+        mode: MerkleTreeMode,
+    ) -> bool {
+        Self::verify_proof_with::<Sha256>(root, leaf, proof, mode)
+    }
+
+    /// Same as [`verify_proof_with_mode`], hashing each node with `H` instead
+    /// of SHA-256.
+    ///
+    /// [`verify_proof_with_mode`]: AccountsHasher::verify_proof_with_mode
+    pub fn verify_proof_with<H: HashFunction>(
+        root: &Hash,
+        leaf: &Hash,
+        proof: &MerkleProof,
+        mode: MerkleTreeMode,
+    ) -> bool {
+        proof.compute_root_with::<H>(leaf, mode).as_ref() == Some(root)
+    }
+
+    /// Generate a proof that `pubkey` has no account with lamports in the tree
+    /// whose root is the accumulated hash of `accounts`.
+    ///
+    /// Returns `None` if `pubkey` has an account with lamports in `accounts`.
+    pub fn generate_exclusion_proof(
+        accounts: Vec<(Pubkey, Account)>,
+        pubkey: &Pubkey,
+    ) -> Option<ExclusionProof> {
+        Self::generate_exclusion_proof_with_mode(accounts, pubkey, MerkleTreeMode::Legacy)
+    }
+
+    /// Same as [`generate_exclusion_proof`], for a tree hashed in the given
+    /// `mode`.
+    ///
+    /// [`generate_exclusion_proof`]: AccountsHasher::generate_exclusion_proof
+    pub fn generate_exclusion_proof_with_mode(
+        accounts: Vec<(Pubkey, Account)>,
+        pubkey: &Pubkey,
+        mode: MerkleTreeMode,
+    ) -> Option<ExclusionProof> {
+        Self::generate_exclusion_proof_with::<Sha256>(accounts, pubkey, mode)
+    }
+
+    /// Same as [`generate_exclusion_proof_with_mode`], for a tree whose nodes
+    /// are hashed with `H` instead of SHA-256.
+    ///
+    /// [`generate_exclusion_proof_with_mode`]: AccountsHasher::generate_exclusion_proof_with_mode
+    pub fn generate_exclusion_proof_with<H: HashFunction>(
+        mut accounts: Vec<(Pubkey, Account)>,
+        pubkey: &Pubkey,
+        mode: MerkleTreeMode,
+    ) -> Option<ExclusionProof> {
+        accounts.sort_unstable_by_key(|a| a.0);
+        let hashes: Vec<Hash> = accounts
+            .iter()
+            .map(|(pubkey, account)| hash_account(account, pubkey).0)
+            .collect();
+
+        let split = accounts.partition_point(|(key, _)| key < pubkey);
+        if accounts
+            .get(split)
+            .is_some_and(|(key, account)| key == pubkey && account.lamports() != 0)
+        {
+            return None;
+        }
+        let start = accounts[..split]
+            .iter()
+            .rposition(|(_, account)| account.lamports() != 0)
+            .map_or(0, |index| index + 1);
+        let end = accounts[split..]
+            .iter()
+            .position(|(_, account)| account.lamports() != 0)
+            .map_or(accounts.len(), |index| split + index);
+
+        let neighbor = |index: usize| {
+            let (pubkey, account) = accounts[index].clone();
+            Self::generate_proof_with::<H>(&hashes, index, MERKLE_FANOUT, mode).map(|proof| {
+                ExclusionProofNeighbor {
+                    pubkey,
+                    account,
+                    proof,
+                }
+            })
+        };
+        let left = start.checked_sub(1).and_then(neighbor);
+        let right = (end < accounts.len()).then(|| neighbor(end)).flatten();
+        let gap = (start..end)
+            .map(|index| Self::generate_proof_with::<H>(&hashes, index, MERKLE_FANOUT, mode))
+            .collect::<Option<_>>()?;
+
+        Some(ExclusionProof { left, gap, right })
+    }
+
+    /// Verify that `pubkey` has no account with lamports in the tree with root
+    /// `root`.
+    pub fn verify_exclusion_proof(root: &Hash, pubkey: &Pubkey, proof: &ExclusionProof) -> bool {
+        Self::verify_exclusion_proof_with_mode(root, pubkey, proof, MerkleTreeMode::Legacy)
+    }
+
+    /// Verify that `pubkey` has no account with lamports in the tree hashed in
+    /// `mode` with root `root`.
+    pub fn verify_exclusion_proof_with_mode(
+        root: &Hash,
+        pubkey: &Pubkey,
+        proof: &ExclusionProof,
+        mode: MerkleTreeMode,
+    ) -> bool {
+        Self::verify_exclusion_proof_with::<Sha256>(root, pubkey, proof, mode)
+    }
+
+    /// Same as [`verify_exclusion_proof_with_mode`], for a tree whose nodes
+    /// are hashed with `H` instead of SHA-256.
+    ///
+    /// [`verify_exclusion_proof_with_mode`]: AccountsHasher::verify_exclusion_proof_with_mode
+    pub fn verify_exclusion_proof_with<H: HashFunction>(
+        root: &Hash,
+        pubkey: &Pubkey,
+        proof: &ExclusionProof,
+        mode: MerkleTreeMode,
+    ) -> bool {
+        let mut leaves = vec![];
+        if let Some(left) = &proof.left {
+            if left.pubkey >= *pubkey || left.account.lamports() == 0 {
+                return false;
+            }
+            leaves.push((hash_account(&left.account, &left.pubkey).0, &left.proof));
+        }
+        leaves.extend(proof.gap.iter().map(|proof| (Hash::default(), proof)));
+        if let Some(right) = &proof.right {
+            if right.pubkey <= *pubkey || right.account.lamports() == 0 {
+                return false;
+            }
+            leaves.push((hash_account(&right.account, &right.pubkey).0, &right.proof));
+        }
+
+        let (Some(first), Some(last)) = (leaves.first(), leaves.last()) else {
+            // only an empty tree has no leaves at all
+            return *root == mode.intermediate_hasher::<H>().result();
+        };
+        if (proof.left.is_none() && !first.1.is_first())
+            || (proof.right.is_none() && !last.1.is_last())
+        {
+            return false;
+        }
+
+        let mut expected_index = None;
+        for (leaf, leaf_proof) in leaves {
+            if !Self::verify_proof_with::<H>(root, &leaf, leaf_proof, mode) {
+                return false;
+            }
+            let Some(index) = leaf_proof.leaf_index(MERKLE_FANOUT) else {
+                return false;
+            };
+            if expected_index.is_some_and(|expected| expected != index) {
+                return false;
+            }
+            expected_index = Some(index + 1);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::merkle_root_builder::GenericMerkleRootBuilder;
+    use crate::hash::{Blake3, Keccak256};
+
+    fn leaves(count: usize) -> Vec<Hash> {
+        (0..count)
+            .map(|i| crate::hash::hash(&i.to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_and_verify_proof() {
+        for fanout in [2, 3, MERKLE_FANOUT] {
+            for count in [1, 2, 3, 15, 16, 17, 255, 256, 257, 300] {
+                let hashes = leaves(count);
+                let root = AccountsHasher::compute_merkle_root_recurse(hashes.clone(), fanout);
+                for (index, leaf) in hashes.iter().enumerate() {
+                    let proof = AccountsHasher::generate_proof(&hashes, index, fanout).unwrap();
+                    assert!(
+                        AccountsHasher::verify_proof(&root, leaf, &proof),
+                        "fanout {fanout} count {count} index {index}"
+                    );
+                    assert!(!AccountsHasher::verify_proof(
+                        &root,
+                        &Hash::new_unique(),
+                        &proof
+                    ));
+                }
+                assert!(AccountsHasher::generate_proof(&hashes, count, fanout).is_none());
+            }
+        }
+
+        let hashes = leaves(10);
+        for fanout in [0, 1] {
+            assert!(AccountsHasher::generate_proof(&hashes, 0, fanout).is_none());
+            assert!(AccountsHasher::generate_proof_with_mode(
+                &hashes,
+                0,
+                fanout,
+                MerkleTreeMode::DomainSeparated
+            )
+            .is_none());
+        }
+    }
+
+    #[test]
+    fn test_verify_proof_rejects_tampering() {
+        let hashes = leaves(40);
+        let root = AccountsHasher::compute_merkle_root_recurse(hashes.clone(), MERKLE_FANOUT);
+        let proof = AccountsHasher::generate_proof(&hashes, 20, MERKLE_FANOUT).unwrap();
+        assert_eq!(proof.levels.len(), 2);
+        assert!(AccountsHasher::verify_proof(&root, &hashes[20], &proof));
+        assert!(!AccountsHasher::verify_proof(&root, &hashes[21], &proof));
+        assert!(!AccountsHasher::verify_proof(
+            &Hash::new_unique(),
+            &hashes[20],
+            &proof
+        ));
+
+        let mut tampered = proof.clone();
+        tampered.levels[1].siblings[0] = Hash::new_unique();
+        assert!(!AccountsHasher::verify_proof(&root, &hashes[20], &tampered));
+
+        let mut tampered = proof.clone();
+        tampered.levels[0].index += 1;
+        assert!(!AccountsHasher::verify_proof(&root, &hashes[20], &tampered));
+
+        let mut tampered = proof;
+        tampered.levels[0].index = 100;
+        assert!(!AccountsHasher::verify_proof(&root, &hashes[20], &tampered));
+
+        assert!(!AccountsHasher::verify_proof(
+            &root,
+            &hashes[20],
+            &MerkleProof::default()
+        ));
+    }
+
+    #[test]
+    fn test_generate_account_proof() {
+        let hashes: Vec<_> = (0..100)
+            .map(|_| (Pubkey::new_unique(), AccountHash(Hash::new_unique())))
+            .rev()
+            .collect();
+        let root = AccountsHasher::accumulate_account_hashes(hashes.clone());
+        for (pubkey, hash) in &hashes {
+            let proof = AccountsHasher::generate_account_proof(hashes.clone(), pubkey).unwrap();
+            assert!(AccountsHasher::verify_proof(&root, &hash.0, &proof));
+        }
+        assert!(AccountsHasher::generate_account_proof(hashes, &Pubkey::new_unique()).is_none());
+    }
+
+    fn accounts(lamports: &[u64]) -> Vec<(Pubkey, Account)> {
+        let mut accounts: Vec<_> = lamports
+            .iter()
+            .map(|&lamports| {
+                let account = Account::new(lamports, 1, &Pubkey::new_unique());
+                (Pubkey::new_unique(), account)
+            })
+            .collect();
+        accounts.sort_unstable_by_key(|a| a.0);
+        accounts
+    }
+
+    fn root(accounts: &[(Pubkey, Account)]) -> Hash {
+        AccountsHasher::accumulate_account_hashes(
+            accounts
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, hash_account(account, pubkey)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_leaf_index() {
+        let hashes = leaves(300);
+        for fanout in [2, 3, MERKLE_FANOUT] {
+            for index in 0..hashes.len() {
+                let proof = AccountsHasher::generate_proof(&hashes, index, fanout).unwrap();
+                assert_eq!(proof.leaf_index(fanout), Some(index));
+                assert_eq!(proof.is_first(), index == 0);
+                assert_eq!(proof.is_last(), index == hashes.len() - 1);
+            }
+        }
+        let proof = AccountsHasher::generate_proof(&hashes, 0, MERKLE_FANOUT).unwrap();
+        assert_eq!(proof.leaf_index(2), None);
+    }
+
+    #[test]
+    fn test_exclusion_proof() {
+        for lamports in [
+            vec![],
+            vec![1],
+            vec![0],
+            vec![1, 2, 3],
+            vec![0, 0, 0],
+            vec![1, 0, 0, 2, 0, 3, 0],
+            (0..100).map(|i| i % 3).collect(),
+        ] {
+            let accounts = accounts(&lamports);
+            let root = root(&accounts);
+
+            let mut pubkeys: Vec<_> = (0..20).map(|_| Pubkey::new_unique()).collect();
+            pubkeys.push(Pubkey::default());
+            pubkeys.push(Pubkey::from([0xff; 32]));
+            for pubkey in &pubkeys {
+                let proof =
+                    AccountsHasher::generate_exclusion_proof(accounts.clone(), pubkey).unwrap();
+                assert!(AccountsHasher::verify_exclusion_proof(
+                    &root, pubkey, &proof
+                ));
+            }
+
+            for (pubkey, account) in &accounts {
+                let proof = AccountsHasher::generate_exclusion_proof(accounts.clone(), pubkey);
+                if account.lamports == 0 {
+                    let proof = proof.unwrap();
+                    assert!(AccountsHasher::verify_exclusion_proof(
+                        &root, pubkey, &proof
+                    ));
+                } else {
+                    assert!(proof.is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_exclusion_proof_rejects_tampering() {
+        let accounts = accounts(&[1, 2, 0, 0, 3, 4, 0, 5]);
+        let root = root(&accounts);
+        let pubkey = Pubkey::new_from_array({
+            let mut bytes = accounts[2].0.to_bytes();
+            bytes[31] = bytes[31].wrapping_add(1);
+            bytes
+        });
+        assert!(pubkey > accounts[2].0 && pubkey < accounts[4].0);
+        let proof = AccountsHasher::generate_exclusion_proof(accounts.clone(), &pubkey).unwrap();
+        assert_eq!(proof.gap.len(), 2);
+        assert!(AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &proof
+        ));
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &Hash::new_unique(),
+            &pubkey,
+            &proof
+        ));
+
+        // outside of the proven range
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root,
+            &accounts[0].0,
+            &proof
+        ));
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root,
+            &accounts[7].0,
+            &proof
+        ));
+
+        // skipping a leaf
+        let mut tampered = proof.clone();
+        tampered.gap.pop();
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &tampered
+        ));
+
+        // dropping a neighbor
+        let mut tampered = proof.clone();
+        tampered.left = None;
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &tampered
+        ));
+        let mut tampered = proof.clone();
+        tampered.right = None;
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &tampered
+        ));
+
+        // changing a neighbor's account
+        let mut tampered = proof.clone();
+        tampered.left.as_mut().unwrap().account.lamports += 1;
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &tampered
+        ));
+
+        // claiming a neighbor's leaf for another pubkey
+        let mut tampered = proof;
+        tampered.right.as_mut().unwrap().pubkey = Pubkey::from([0xff; 32]);
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &tampered
+        ));
+
+        // the empty tree proof only holds for the empty root
+        let empty = ExclusionProof {
+            left: None,
+            gap: vec![],
+            right: None,
+        };
+        assert!(!AccountsHasher::verify_exclusion_proof(
+            &root, &pubkey, &empty
+        ));
+        assert!(AccountsHasher::verify_exclusion_proof(
+            &AccountsHasher::accumulate_account_hashes(vec![]),
+            &pubkey,
+            &empty
+        ));
+    }
+
+    #[test]
+    fn test_exclusion_proof_hash_functions() {
+        let accounts = accounts(&[1, 0, 2, 0, 0, 3]);
+        let root = AccountsHasher::compute_merkle_root_with::<Blake3>(
+            accounts
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, hash_account(account, pubkey).0))
+                .collect(),
+            MERKLE_FANOUT,
+        );
+        for (pubkey, account) in &accounts {
+            let proof = AccountsHasher::generate_exclusion_proof_with::<Blake3>(
+                accounts.clone(),
+                pubkey,
+                MerkleTreeMode::Legacy,
+            );
+            if account.lamports == 0 {
+                let proof = proof.unwrap();
+                assert!(AccountsHasher::verify_exclusion_proof_with::<Blake3>(
+                    &root,
+                    pubkey,
+                    &proof,
+                    MerkleTreeMode::Legacy
+                ));
+                assert!(!AccountsHasher::verify_exclusion_proof(
+                    &root, pubkey, &proof
+                ));
+            } else {
+                assert!(proof.is_none());
+            }
+        }
+
+        let empty = AccountsHasher::generate_exclusion_proof_with::<Keccak256>(
+            vec![],
+            &Pubkey::new_unique(),
+            MerkleTreeMode::Legacy,
+        )
+        .unwrap();
+        assert!(AccountsHasher::verify_exclusion_proof_with::<Keccak256>(
+            &AccountsHasher::compute_merkle_root_with::<Keccak256>(vec![], MERKLE_FANOUT),
+            &Pubkey::new_unique(),
+            &empty,
+            MerkleTreeMode::Legacy
+        ));
+        assert!(!AccountsHasher::verify_exclusion_proof_with::<Keccak256>(
+            &AccountsHasher::accumulate_account_hashes(vec![]),
+            &Pubkey::new_unique(),
+            &empty,
+            MerkleTreeMode::Legacy
+        ));
+    }
+
+    #[test]
+    fn test_generate_and_verify_multi_proof() {
+        for fanout in [2, 3, MERKLE_FANOUT] {
+            for count in [1, 2, 15, 16, 17, 256, 257, 1000] {
+                let hashes = leaves(count);
+                let root = AccountsHasher::compute_merkle_root_recurse(hashes.clone(), fanout);
+                for indices in [
+                    vec![0],
+                    vec![count - 1],
+                    vec![0, count - 1],
+                    (0..count).collect(),
+                    (0..count).step_by(7).collect(),
+                    (0..count).rev().step_by(40).collect(),
+                ] {
+                    let proof =
+                        AccountsHasher::generate_multi_proof(&hashes, &indices, fanout).unwrap();
+                    let proven: Vec<_> = proof.indices.iter().map(|&i| hashes[i]).collect();
+                    assert!(
+                        AccountsHasher::verify_multi_proof(&root, &proven, &proof, fanout),
+                        "fanout {fanout} count {count} indices {indices:?}"
+                    );
+                }
+                assert!(AccountsHasher::generate_multi_proof(&hashes, &[], fanout).is_none());
+                assert!(AccountsHasher::generate_multi_proof(&hashes, &[count], fanout).is_none());
+            }
+        }
+
+        let hashes = leaves(10);
+        for fanout in [0, 1] {
+            assert!(AccountsHasher::generate_multi_proof(&hashes, &[0, 3], fanout).is_none());
+        }
+    }
+
+    fn check_multi_proof_hash_function<H: HashFunction>() {
+        for mode in [MerkleTreeMode::Legacy, MerkleTreeMode::DomainSeparated] {
+            for fanout in [2, MERKLE_FANOUT] {
+                let hashes = leaves(300);
+                let mut builder = GenericMerkleRootBuilder::<H>::new_with_mode(fanout, mode);
+                builder.extend(hashes.iter().copied());
+                let root = builder.finish();
+                let proof = AccountsHasher::generate_multi_proof_with::<H>(
+                    &hashes,
+                    &[0, 17, 299],
+                    fanout,
+                    mode,
+                )
+                .unwrap();
+                let proven = [hashes[0], hashes[17], hashes[299]];
+                assert!(
+                    AccountsHasher::verify_multi_proof_with::<H>(
+                        &root, &proven, &proof, fanout, mode
+                    ),
+                    "mode {mode:?} fanout {fanout}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_proof_hash_functions() {
+        check_multi_proof_hash_function::<Sha256>();
+        check_multi_proof_hash_function::<Blake3>();
+        check_multi_proof_hash_function::<Keccak256>();
+
+        let hashes = leaves(40);
+        let root = AccountsHasher::compute_merkle_root_with::<Blake3>(
+            hashes.iter().map(|h| (Pubkey::new_unique(), *h)).collect(),
+            MERKLE_FANOUT,
+        );
+        let proof = AccountsHasher::generate_multi_proof_with::<Blake3>(
+            &hashes,
+            &[1, 2],
+            MERKLE_FANOUT,
+            MerkleTreeMode::Legacy,
+        )
+        .unwrap();
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &[hashes[1], hashes[2]],
+            &proof,
+            MERKLE_FANOUT
+        ));
+        assert!(AccountsHasher::generate_multi_proof_with::<Blake3>(
+            &hashes,
+            &[1],
+            1,
+            MerkleTreeMode::Legacy
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_multi_proof_is_compact() {
+        let hashes = leaves(100_000);
+        let indices: Vec<_> = (0..60).map(|i| i * 1_601).collect();
+        let proof = AccountsHasher::generate_multi_proof(&hashes, &indices, MERKLE_FANOUT).unwrap();
+        let single: usize = indices
+            .iter()
+            .map(|&i| {
+                let proof = AccountsHasher::generate_proof(&hashes, i, MERKLE_FANOUT).unwrap();
+                proof.levels.iter().map(|l| l.siblings.len()).sum::<usize>()
+            })
+            .sum();
+        assert!(proof.hashes.len() < single);
+
+        // adjacent leaves need no siblings from each other
+        let proof = AccountsHasher::generate_multi_proof(&hashes, &[0, 1], MERKLE_FANOUT).unwrap();
+        let single = AccountsHasher::generate_proof(&hashes, 0, MERKLE_FANOUT).unwrap();
+        assert_eq!(
+            proof.hashes.len() + 1,
+            single
+                .levels
+                .iter()
+                .map(|l| l.siblings.len())
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_verify_multi_proof_rejects_tampering() {
+        let hashes = leaves(300);
+        let root = AccountsHasher::compute_merkle_root_recurse(hashes.clone(), MERKLE_FANOUT);
+        let proof =
+            AccountsHasher::generate_multi_proof(&hashes, &[3, 17, 250], MERKLE_FANOUT).unwrap();
+        let proven = [hashes[3], hashes[17], hashes[250]];
+        assert!(AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &proof,
+            MERKLE_FANOUT
+        ));
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &[hashes[3], hashes[18], hashes[250]],
+            &proof,
+            MERKLE_FANOUT
+        ));
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven[..2],
+            &proof,
+            MERKLE_FANOUT
+        ));
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &proof,
+            MERKLE_FANOUT - 1
+        ));
+
+        let mut tampered = proof.clone();
+        tampered.hashes[0] = Hash::new_unique();
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &tampered,
+            MERKLE_FANOUT
+        ));
+
+        let mut tampered = proof.clone();
+        tampered.hashes.push(Hash::new_unique());
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &tampered,
+            MERKLE_FANOUT
+        ));
+
+        let mut tampered = proof.clone();
+        tampered.hashes.pop();
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &tampered,
+            MERKLE_FANOUT
+        ));
+
+        let mut tampered = proof.clone();
+        tampered.indices = vec![17, 3, 250];
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &tampered,
+            MERKLE_FANOUT
+        ));
+
+        let mut tampered = proof;
+        tampered.leaf_count = 250;
+        assert!(!AccountsHasher::verify_multi_proof(
+            &root,
+            &proven,
+            &tampered,
+            MERKLE_FANOUT
+        ));
+
+        // positions near the end of the address space must not overflow
+        let crafted = MerkleMultiProof {
+            leaf_count: usize::MAX,
+            indices: vec![usize::MAX - 1],
+            hashes: vec![],
+        };
+        assert_eq!(
+            crafted.compute_root(&[Hash::default()], MERKLE_FANOUT),
+            None
+        );
+        let crafted = MerkleMultiProof {
+            hashes: vec![Hash::default(); 1000],
+            ..crafted
+        };
+        assert_eq!(
+            crafted.compute_root(&[Hash::default()], MERKLE_FANOUT),
+            None
+        );
+    }
+
+    #[test]
+    fn test_generate_account_multi_proof() {
+        let hashes: Vec<_> = (0..100)
+            .map(|_| (Pubkey::new_unique(), AccountHash(Hash::new_unique())))
+            .rev()
+            .collect();
+        let root = AccountsHasher::accumulate_account_hashes(hashes.clone());
+        let mut selected: Vec<_> = hashes.iter().step_by(3).copied().collect();
+        let pubkeys: Vec<_> = selected.iter().map(|(pubkey, _)| *pubkey).collect();
+        let proof = AccountsHasher::generate_account_multi_proof(hashes.clone(), &pubkeys).unwrap();
+        selected.sort_unstable_by_key(|a| a.0);
+        let leaves: Vec<_> = selected.iter().map(|(_, hash)| hash.0).collect();
+        assert!(AccountsHasher::verify_multi_proof(
+            &root,
+            &leaves,
+            &proof,
+            MERKLE_FANOUT
+        ));
+        assert!(
+            AccountsHasher::generate_account_multi_proof(hashes, &[Pubkey::new_unique()]).is_none()
+        );
+    }
+
+    #[test]
+    fn test_domain_separated_proof() {
+        let mode = MerkleTreeMode::DomainSeparated;
+        for count in [1, 2, 17, 300] {
+            let hashes: Vec<_> = leaves(count)
+                .into_iter()
+                .map(|h| (Pubkey::new_unique(), h))
+                .collect();
+            let leaves: Vec<_> = hashes.iter().map(|(_, h)| *h).collect();
+            let root = AccountsHasher::compute_merkle_root_with_mode(hashes, MERKLE_FANOUT, mode);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof =
+                    AccountsHasher::generate_proof_with_mode(&leaves, index, MERKLE_FANOUT, mode)
+                        .unwrap();
+                assert!(AccountsHasher::verify_proof_with_mode(
+                    &root, leaf, &proof, mode
+                ));
+                assert!(!AccountsHasher::verify_proof(&root, leaf, &proof));
+            }
+        }
+
+        // an inner node can be passed off as a leaf in the legacy mode only
+        let leaves = leaves(32);
+        for mode in [MerkleTreeMode::Legacy, MerkleTreeMode::DomainSeparated] {
+            let root = AccountsHasher::compute_merkle_root_with_mode(
+                leaves.iter().map(|h| (Pubkey::new_unique(), *h)).collect(),
+                MERKLE_FANOUT,
+                mode,
+            );
+            let proof =
+                AccountsHasher::generate_proof_with_mode(&leaves, 0, MERKLE_FANOUT, mode).unwrap();
+            let (lower, upper) = proof.levels.split_at(1);
+            let inner_node = MerkleProof {
+                levels: lower.to_vec(),
+            }
+            .compute_root_with_mode(&leaves[0], mode)
+            .unwrap();
+            let inner_proof = MerkleProof {
+                levels: upper.to_vec(),
+            };
+            assert_eq!(
+                AccountsHasher::verify_proof_with_mode(&root, &inner_node, &inner_proof, mode),
+                mode == MerkleTreeMode::Legacy
+            );
+        }
+    }
+
+    #[test]
+    fn test_domain_separated_multi_proof() {
+        let mode = MerkleTreeMode::DomainSeparated;
+        for count in [1, 2, 17, 300] {
+            let hashes = leaves(count);
+            let root = AccountsHasher::compute_merkle_root_with_mode(
+                hashes.iter().map(|h| (Pubkey::new_unique(), *h)).collect(),
+                MERKLE_FANOUT,
+                mode,
+            );
+            let indices: Vec<_> = (0..count).step_by(7).collect();
+            let proof = AccountsHasher::generate_multi_proof_with_mode(
+                &hashes,
+                &indices,
+                MERKLE_FANOUT,
+                mode,
+            )
+            .unwrap();
+            let proven: Vec<_> = proof.indices.iter().map(|&i| hashes[i]).collect();
+            assert!(AccountsHasher::verify_multi_proof_with_mode(
+                &root,
+                &proven,
+                &proof,
+                MERKLE_FANOUT,
+                mode
+            ));
+            assert!(!AccountsHasher::verify_multi_proof(
+                &root,
+                &proven,
+                &proof,
+                MERKLE_FANOUT
+            ));
+        }
+    }
+
+    #[test]
+    fn test_domain_separated_exclusion_proof() {
+        let mode = MerkleTreeMode::DomainSeparated;
+        let accounts = accounts(&[1, 0, 2, 0, 0, 3]);
+        let root = AccountsHasher::accumulate_account_hashes_with_mode(
+            accounts
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, hash_account(account, pubkey)))
+                .collect(),
+            mode,
+        );
+        for (pubkey, account) in &accounts {
+            let proof =
+                AccountsHasher::generate_exclusion_proof_with_mode(accounts.clone(), pubkey, mode);
+            if account.lamports == 0 {
+                let proof = proof.unwrap();
+                assert!(AccountsHasher::verify_exclusion_proof_with_mode(
+                    &root, pubkey, &proof, mode
+                ));
+                assert!(!AccountsHasher::verify_exclusion_proof(
+                    &root, pubkey, &proof
+                ));
+            } else {
+                assert!(proof.is_none());
+            }
+        }
+
+        let empty =
+            AccountsHasher::generate_exclusion_proof_with_mode(vec![], &Pubkey::new_unique(), mode)
+                .unwrap();
+        assert!(AccountsHasher::verify_exclusion_proof_with_mode(
+            &AccountsHasher::accumulate_account_hashes_with_mode(vec![], mode),
+            &Pubkey::new_unique(),
+            &empty,
+            mode
+        ));
+        assert!(!AccountsHasher::verify_exclusion_proof_with_mode(
+            &AccountsHasher::accumulate_account_hashes(vec![]),
+            &Pubkey::new_unique(),
+            &empty,
+            mode
+        ));
+    }
+
+    fn check_proof_hash_function<H: HashFunction>() {
+        for mode in [MerkleTreeMode::Legacy, MerkleTreeMode::DomainSeparated] {
+            for count in [1, 2, 17, 300] {
+                let hashes = leaves(count);
+                let mut builder = GenericMerkleRootBuilder::<H>::new_with_mode(MERKLE_FANOUT, mode);
+                builder.extend(hashes.iter().copied());
+                let root = builder.finish();
+                for (index, leaf) in hashes.iter().enumerate() {
+                    let proof = AccountsHasher::generate_proof_with::<H>(
+                        &hashes,
+                        index,
+                        MERKLE_FANOUT,
+                        mode,
+                    )
+                    .unwrap();
+                    assert!(
+                        AccountsHasher::verify_proof_with::<H>(&root, leaf, &proof, mode),
+                        "mode {mode:?} count {count} index {index}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_hash_functions() {
+        check_proof_hash_function::<Sha256>();
+        check_proof_hash_function::<Blake3>();
+        check_proof_hash_function::<Keccak256>();
+
+        let hashes = leaves(40);
+        let root = AccountsHasher::compute_merkle_root_with::<Blake3>(
+            hashes.iter().map(|h| (Pubkey::new_unique(), *h)).collect(),
+            MERKLE_FANOUT,
+        );
+        let proof = AccountsHasher::generate_proof_with::<Blake3>(
+            &hashes,
+            20,
+            MERKLE_FANOUT,
+            MerkleTreeMode::Legacy,
+        )
+        .unwrap();
+        assert!(AccountsHasher::verify_proof_with::<Blake3>(
+            &root,
+            &hashes[20],
+            &proof,
+            MerkleTreeMode::Legacy
+        ));
+        assert!(!AccountsHasher::verify_proof(&root, &hashes[20], &proof));
+    }
+}
@@ -0,0 +1,233 @@
+//! Computing the root of the [`AccountsHasher`] merkle tree from a stream of
+//! leaves.
+//!
+//! Building a level of the tree only needs the current run of up to `fanout`
+//! nodes of each level below it, so the leaves never have to be held in memory
+//! at once.
+
+use thiserror::Error;
+
+use super::account_hasher::{AccountHash, AccountsHasher, MerkleTreeMode, MERKLE_FANOUT};
+use crate::hash::{GenericHasher, Hash, HashFunction, Sha256};
+use crate::pubkey::Pubkey;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AccumulateError {
+    #[error("account {0} is not sorted after the previous account")]
+    Unsorted(Pubkey),
+}
+
+/// The node being built on one level of the tree.
+#[derive(Clone, Default)]
+struct PartialNode<H: HashFunction> {
+    hasher: GenericHasher<H>,
+    /// Number of children hashed into `hasher`.
+    len: usize,
+    /// The first child hashed into `hasher`.
+    first: Hash,
+    /// Number of children this level has received in total.
+    total: usize,
+}
+
+/// Computes the same root as [`AccountsHasher::compute_merkle_root_loop_with`]
+/// over leaves pushed one at a time, in tree order.
+///
+/// Memory use is one partial node per level, independent of the number of
+/// leaves.
+#[derive(Clone)]
+pub struct GenericMerkleRootBuilder<H: HashFunction> {
+    fanout: usize,
+    mode: MerkleTreeMode,
+    /// `levels[k]` collects the children of the nodes `k + 1` levels above the
+    /// leaves.
+    levels: Vec<PartialNode<H>>,
+}
+
+/// A [`GenericMerkleRootBuilder`] hashing with SHA-256, like
+/// [`AccountsHasher::compute_merkle_root_loop`].
+pub type MerkleRootBuilder = GenericMerkleRootBuilder<Sha256>;
+
+impl<H: HashFunction> GenericMerkleRootBuilder<H> {
+    pub fn new(fanout: usize) -> Self {
+        Self::new_with_mode(fanout, MerkleTreeMode::Legacy)
+    }
+
+    pub fn new_with_mode(fanout: usize, mode: MerkleTreeMode) -> Self {
+        assert!(fanout >= 2, "fanout must be at least 2");
+        Self {
+            fanout,
+            mode,
+            levels: vec![],
+        }
+    }
+
+    /// Number of leaves pushed so far.
+    pub fn len(&self) -> usize {
+        self.levels.first().map_or(0, |level| level.total)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append the next leaf of the tree.
+    pub fn push(&mut self, leaf: &Hash) {
+        let node = self.mode.leaf_node::<H>(leaf);
+        self.push_node(0, node);
+    }
+
+    fn push_node(&mut self, depth: usize, node: Hash) {
+        if depth == self.levels.len() {
+            self.levels.push(PartialNode::default());
+        }
+        let level = &mut self.levels[depth];
+        if level.len == 0 {
+            level.hasher = self.mode.intermediate_hasher::<H>();
+            level.first = node;
+        }
+        level.hasher.hash(node.as_ref());
+        level.len += 1;
+        level.total += 1;
+        if level.len == self.fanout {
+            let parent = self.flush(depth);
+            self.push_node(depth + 1, parent);
+        }
+    }
+
+    fn flush(&mut self, depth: usize) -> Hash {
+        let level = &mut self.levels[depth];
+        level.len = 0;
+        std::mem::take(&mut level.hasher).result()
+    }
+
+    /// The root of the tree over all pushed leaves.
+    pub fn finish(mut self) -> Hash {
+        if self.is_empty() {
+            return self.mode.intermediate_hasher::<H>().result();
+        }
+        let mut depth = 0;
+        loop {
+            if self.levels[depth].len > 0 {
+                let parent = self.flush(depth);
+                self.push_node(depth + 1, parent);
+            }
+            depth += 1;
+            // the first level with a single node holds the root
+            let level = &self.levels[depth];
+            if level.total == 1 {
+                return level.first;
+            }
+        }
+    }
+}
+
+impl<H: HashFunction> Default for GenericMerkleRootBuilder<H> {
+    fn default() -> Self {
+        Self::new(MERKLE_FANOUT)
+    }
+}
+
+impl<H: HashFunction> Extend<Hash> for GenericMerkleRootBuilder<H> {
+    fn extend<I: IntoIterator<Item = Hash>>(&mut self, iter: I) {
+        for leaf in iter {
+            self.push(&leaf);
+        }
+    }
+}
+
+impl AccountsHasher {
+    /// Same as [`accumulate_account_hashes`] for account hashes that are
+    /// already sorted by pubkey, without collecting them first.
+    ///
+    /// Fails if the pubkeys are not strictly increasing.
+    ///
+    /// [`accumulate_account_hashes`]: AccountsHasher::accumulate_account_hashes
+    pub fn accumulate_sorted_account_hashes<I>(hashes: I) -> Result<Hash, AccumulateError>
+    where
+        I: IntoIterator<Item = (Pubkey, AccountHash)>,
+    {
+        let mut builder = MerkleRootBuilder::new(MERKLE_FANOUT);
+        let mut previous = None;
+        for (pubkey, hash) in hashes {
+            if previous.is_some_and(|previous| previous >= pubkey) {
+                return Err(AccumulateError::Unsorted(pubkey));
+            }
+            previous = Some(pubkey);
+            builder.push(&hash.0);
+        }
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{Blake3, Keccak256};
+
+    #[test]
+    fn test_matches_compute_merkle_root() {
+        for mode in [MerkleTreeMode::Legacy, MerkleTreeMode::DomainSeparated] {
+            for fanout in [2, 3, MERKLE_FANOUT] {
+                for count in [0, 1, 2, 3, 4, 15, 16, 17, 255, 256, 257, 4096, 5000] {
+                    let hashes: Vec<_> = (0..count)
+                        .map(|i: usize| (Pubkey::new_unique(), crate::hash::hash(&i.to_le_bytes())))
+                        .collect();
+                    let mut builder = MerkleRootBuilder::new_with_mode(fanout, mode);
+                    builder.extend(hashes.iter().map(|(_, hash)| *hash));
+                    assert_eq!(builder.len(), count);
+                    assert_eq!(
+                        builder.finish(),
+                        AccountsHasher::compute_merkle_root_with_mode(hashes, fanout, mode),
+                        "mode {mode:?} fanout {fanout} count {count}"
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_hash_function<H: HashFunction>() {
+        for count in [0, 1, 16, 17, 300] {
+            let hashes: Vec<_> = (0..count)
+                .map(|_| (Pubkey::new_unique(), Hash::new_unique()))
+                .collect();
+            let mut builder = GenericMerkleRootBuilder::<H>::default();
+            builder.extend(hashes.iter().map(|(_, hash)| *hash));
+            assert_eq!(
+                builder.finish(),
+                AccountsHasher::compute_merkle_root_with::<H>(hashes, MERKLE_FANOUT),
+                "count {count}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_functions() {
+        check_hash_function::<Sha256>();
+        check_hash_function::<Blake3>();
+        check_hash_function::<Keccak256>();
+    }
+
+    #[test]
+    fn test_accumulate_sorted_account_hashes() {
+        let mut hashes: Vec<_> = (0..1000)
+            .map(|_| (Pubkey::new_unique(), AccountHash(Hash::new_unique())))
+            .collect();
+        let expected = AccountsHasher::accumulate_account_hashes(hashes.clone());
+        assert_eq!(
+            AccountsHasher::accumulate_sorted_account_hashes(hashes.clone()),
+            Ok(expected)
+        );
+
+        hashes.swap(10, 11);
+        assert_eq!(
+            AccountsHasher::accumulate_sorted_account_hashes(hashes.clone()),
+            Err(AccumulateError::Unsorted(hashes[11].0))
+        );
+        hashes.swap(10, 11);
+        hashes[11].0 = hashes[10].0;
+        assert_eq!(
+            AccountsHasher::accumulate_sorted_account_hashes(hashes.clone()),
+            Err(AccumulateError::Unsorted(hashes[11].0))
+        );
+    }
+}
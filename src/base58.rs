@@ -0,0 +1,237 @@
+//! Allocation-free base58 encoding and decoding of fixed-size byte arrays.
+//!
+//! Uses the Bitcoin alphabet, as do Solana pubkeys, hashes and signatures.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use serde::de::value::SeqAccessDeserializer;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const INVALID_DIGIT: u8 = 0xff;
+
+const DECODE_MAP: [u8; 128] = {
+    let mut map = [INVALID_DIGIT; 128];
+    let mut i = 0;
+    while i < ALPHABET.len() {
+        map[ALPHABET[i] as usize] = i as u8;
+        i += 1;
+    }
+    map
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DecodeError {
+    /// The input contains a character outside of the base58 alphabet.
+    InvalidCharacter,
+    /// The input does not decode to exactly `N` bytes.
+    WrongSize,
+}
+
+/// Encode `input` into `output`, returning the number of bytes written.
+///
+/// `output` must be large enough for the longest encoding of `N` bytes, which
+/// is 44 characters for 32 bytes.
+pub(crate) fn encode<const N: usize, const M: usize>(
+    input: &[u8; N],
+    output: &mut [u8; M],
+) -> usize {
+    // base58 digits of the input, least significant first
+    let mut digits = [0u8; M];
+    let mut digits_len = 0;
+    for &byte in input {
+        // digits = digits * 256 + byte
+        let mut carry = byte as u32;
+        for digit in digits[..digits_len].iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits[digits_len] = (carry % 58) as u8;
+            digits_len += 1;
+            carry /= 58;
+        }
+    }
+
+    let leading_zeros = input.iter().take_while(|&&byte| byte == 0).count();
+    output[..leading_zeros].fill(ALPHABET[0]);
+    for (out, &digit) in output[leading_zeros..leading_zeros + digits_len]
+        .iter_mut()
+        .zip(digits[..digits_len].iter().rev())
+    {
+        *out = ALPHABET[digit as usize];
+    }
+    leading_zeros + digits_len
+}
+
+/// Decode `input` into exactly `N` bytes.
+///
+/// Leading `'1'` characters encode leading zero bytes, so the input is only
+/// accepted if the decoded length, including those zeros, is exactly `N`.
+/// Invalid characters are reported before size mismatches, matching the
+/// behavior of decoding with `bs58` and checking the length afterwards.
+pub(crate) const fn decode<const N: usize>(input: &[u8]) -> Result<[u8; N], DecodeError> {
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] >= 128 || DECODE_MAP[input[i] as usize] == INVALID_DIGIT {
+            return Err(DecodeError::InvalidCharacter);
+        }
+        i += 1;
+    }
+
+    let mut output = [0u8; N];
+    let mut leading_ones = 0;
+    let mut leading = true;
+    let mut i = 0;
+    while i < input.len() {
+        let digit = DECODE_MAP[input[i] as usize];
+        if leading && digit == 0 {
+            leading_ones += 1;
+        } else {
+            leading = false;
+        }
+
+        // output = output * 58 + digit, as a big-endian integer
+        let mut carry = digit as u32;
+        let mut j = N;
+        while j > 0 {
+            j -= 1;
+            carry += output[j] as u32 * 58;
+            output[j] = carry as u8;
+            carry >>= 8;
+        }
+        if carry != 0 {
+            return Err(DecodeError::WrongSize);
+        }
+        i += 1;
+    }
+
+    let mut leading_zeros = 0;
+    while leading_zeros < N && output[leading_zeros] == 0 {
+        leading_zeros += 1;
+    }
+    if leading_zeros != leading_ones {
+        return Err(DecodeError::WrongSize);
+    }
+    Ok(output)
+}
+
+/// Serde visitor for 32-byte values that are base58 strings in human-readable
+/// formats and raw bytes otherwise.
+///
+/// Human-readable input may be either a base58 string or an array of 32
+/// numbers, so fixtures written before strings were emitted keep loading.
+pub(crate) struct Base58Visitor<T>(PhantomData<T>);
+
+impl<T> Base58Visitor<T> {
+    pub(crate) fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<'de, T> Visitor<'de> for Base58Visitor<T>
+where
+    T: FromStr + From<[u8; 32]>,
+    T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a base58 string or 32 bytes")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(self)
+        } else {
+            <[u8; 32]>::deserialize(deserializer).map(T::from)
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+        <[u8; 32]>::try_from(v)
+            .map(T::from)
+            .map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<T, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        <[u8; 32]>::deserialize(SeqAccessDeserializer::new(seq)).map(T::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_matches_bs58() {
+        let mut output = [0u8; 44];
+        for input in [[0u8; 32], [0xff; 32], [1; 32]] {
+            let len = encode(&input, &mut output);
+            assert_eq!(&output[..len], bs58::encode(input).into_string().as_bytes());
+        }
+        for _ in 0..100 {
+            let mut input = rand::random::<[u8; 32]>();
+            input[..rand::random::<usize>() % 4].fill(0);
+            let len = encode(&input, &mut output);
+            assert_eq!(&output[..len], bs58::encode(input).into_string().as_bytes());
+            assert_eq!(decode::<32>(&output[..len]), Ok(input));
+        }
+    }
+
+    #[test]
+    fn test_decode_matches_bs58() {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate().skip(3) {
+            *byte = (i * 37) as u8;
+        }
+        for input in [[0u8; 32], [0xff; 32], bytes] {
+            let encoded = bs58::encode(input).into_string();
+            assert_eq!(decode::<32>(encoded.as_bytes()), Ok(input));
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode::<32>(b"0"), Err(DecodeError::InvalidCharacter));
+        assert_eq!(
+            decode::<32>("☉".as_bytes()),
+            Err(DecodeError::InvalidCharacter)
+        );
+        assert_eq!(decode::<32>(b""), Err(DecodeError::WrongSize));
+        assert_eq!(decode::<32>(b"2"), Err(DecodeError::WrongSize));
+
+        // one byte too many
+        let encoded = bs58::encode([1u8; 33]).into_string();
+        assert_eq!(
+            decode::<32>(encoded.as_bytes()),
+            Err(DecodeError::WrongSize)
+        );
+        // one leading zero byte too many
+        let encoded = bs58::encode([0u8; 33]).into_string();
+        assert_eq!(
+            decode::<32>(encoded.as_bytes()),
+            Err(DecodeError::WrongSize)
+        );
+        // one byte too few
+        let encoded = bs58::encode([1u8; 31]).into_string();
+        assert_eq!(
+            decode::<32>(encoded.as_bytes()),
+            Err(DecodeError::WrongSize)
+        );
+    }
+}
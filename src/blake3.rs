@@ -0,0 +1,228 @@
+//! Hashing with the [blake3] hash function.
+//!
+//! This mirrors the SHA-256 API in [`hash`], returning the same [`Hash`] type.
+//! [`hash_many`] additionally hashes many short inputs side by side in SIMD
+//! lanes.
+//!
+//! [blake3]: https://github.com/BLAKE3-team/BLAKE3
+//! [`hash`]: crate::hash
+//! [`Hash`]: struct@Hash
+
+use crate::hash::{self, Blake3, GenericHasher, Hash};
+
+/// An incremental blake3 hasher.
+pub type Hasher = GenericHasher<Blake3>;
+
+/// Return a blake3 hash for the given data.
+pub fn hashv(vals: &[&[u8]]) -> Hash {
+    hash::hashv_with::<Blake3>(vals)
+}
+
+/// Return a blake3 hash for the given data.
+pub fn hash(val: &[u8]) -> Hash {
+    hashv(&[val])
+}
+
+/// Return the hash of the given hash extended with the given value.
+pub fn extend_and_hash(id: &Hash, val: &[u8]) -> Hash {
+    hash::extend_and_hash_with::<Blake3>(id, val)
+}
+
+/// Length of a blake3 chunk. Inputs up to this length are hashed with a
+/// single chunk, and can share SIMD lanes in [`hash_many`].
+pub const CHUNK_LEN: usize = 1024;
+
+const BLOCK_LEN: usize = 64;
+/// Number of inputs compressed side by side.
+const LANES: usize = 8;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const ROOT: u32 = 1 << 3;
+
+/// One word of state for each lane. Operating on whole arrays lets the
+/// compiler vectorize every step across the lanes.
+type Words = [u32; LANES];
+
+#[inline(always)]
+fn add(a: Words, b: Words) -> Words {
+    std::array::from_fn(|lane| a[lane].wrapping_add(b[lane]))
+}
+
+#[inline(always)]
+fn xor_rotr(a: Words, b: Words, n: u32) -> Words {
+    std::array::from_fn(|lane| (a[lane] ^ b[lane]).rotate_right(n))
+}
+
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+fn g(state: &mut [Words; 16], a: usize, b: usize, c: usize, d: usize, x: Words, y: Words) {
+    state[a] = add(add(state[a], state[b]), x);
+    state[d] = xor_rotr(state[d], state[a], 16);
+    state[c] = add(state[c], state[d]);
+    state[b] = xor_rotr(state[b], state[c], 12);
+    state[a] = add(add(state[a], state[b]), y);
+    state[d] = xor_rotr(state[d], state[a], 8);
+    state[c] = add(state[c], state[d]);
+    state[b] = xor_rotr(state[b], state[c], 7);
+}
+
+/// The blake3 compression function of the first chunk, in every lane.
+fn compress(cv: &[Words; 8], mut m: [Words; 16], block_len: Words, flags: Words) -> [Words; 8] {
+    let mut state = [[0; LANES]; 16];
+    state[..8].copy_from_slice(cv);
+    for (word, iv) in state[8..12].iter_mut().zip(IV) {
+        *word = [iv; LANES];
+    }
+    // state[12] and state[13] hold the chunk counter, always 0
+    state[14] = block_len;
+    state[15] = flags;
+    for round in 0..7 {
+        g(&mut state, 0, 4, 8, 12, m[0], m[1]);
+        g(&mut state, 1, 5, 9, 13, m[2], m[3]);
+        g(&mut state, 2, 6, 10, 14, m[4], m[5]);
+        g(&mut state, 3, 7, 11, 15, m[6], m[7]);
+        g(&mut state, 0, 5, 10, 15, m[8], m[9]);
+        g(&mut state, 1, 6, 11, 12, m[10], m[11]);
+        g(&mut state, 2, 7, 8, 13, m[12], m[13]);
+        g(&mut state, 3, 4, 9, 14, m[14], m[15]);
+        if round < 6 {
+            m = std::array::from_fn(|i| m[MSG_PERMUTATION[i]]);
+        }
+    }
+    std::array::from_fn(|i| std::array::from_fn(|lane| state[i][lane] ^ state[i + 8][lane]))
+}
+
+/// Hash up to [`LANES`] inputs of at most [`CHUNK_LEN`] bytes side by side.
+fn hash_lanes(inputs: &[&[u8]], hashes: &mut [Hash]) {
+    debug_assert!(inputs.len() <= LANES && inputs.len() == hashes.len());
+    let mut blocks = [0usize; LANES];
+    for (blocks, input) in blocks.iter_mut().zip(inputs) {
+        *blocks = input.len().div_ceil(BLOCK_LEN).max(1);
+    }
+
+    let mut cv = IV.map(|word| [word; LANES]);
+    for block in 0..blocks.iter().copied().max().unwrap_or(0) {
+        let mut m = [[0; LANES]; 16];
+        let mut block_len = [0; LANES];
+        let mut flags = [0; LANES];
+        for (lane, input) in inputs.iter().enumerate() {
+            if block >= blocks[lane] {
+                continue;
+            }
+            let start = block * BLOCK_LEN;
+            let bytes = &input[start..std::cmp::min(start + BLOCK_LEN, input.len())];
+            let mut buf = [0u8; BLOCK_LEN];
+            buf[..bytes.len()].copy_from_slice(bytes);
+            for (word, bytes) in m.iter_mut().zip(buf.chunks_exact(4)) {
+                word[lane] = u32::from_le_bytes(bytes.try_into().unwrap());
+            }
+            block_len[lane] = bytes.len() as u32;
+            if block == 0 {
+                flags[lane] |= CHUNK_START;
+            }
+            if block == blocks[lane] - 1 {
+                flags[lane] |= CHUNK_END | ROOT;
+            }
+        }
+
+        let out = compress(&cv, m, block_len, flags);
+        for lane in (0..inputs.len()).filter(|&lane| block < blocks[lane]) {
+            for (cv, out) in cv.iter_mut().zip(&out) {
+                cv[lane] = out[lane];
+            }
+        }
+    }
+
+    for (lane, hash) in hashes.iter_mut().enumerate() {
+        let mut bytes = [0u8; 32];
+        for (bytes, cv) in bytes.chunks_exact_mut(4).zip(&cv) {
+            bytes.copy_from_slice(&cv[lane].to_le_bytes());
+        }
+        *hash = Hash::new_from_array(bytes);
+    }
+}
+
+/// Return the blake3 hash of each of the given inputs.
+///
+/// Inputs of at most [`CHUNK_LEN`] bytes are hashed several at a time in SIMD
+/// lanes, the way blake3 hashes the chunks of a single long input. Longer
+/// inputs are hashed one by one with [`hash`].
+pub fn hash_many(inputs: &[&[u8]]) -> Vec<Hash> {
+    let mut hashes = vec![Hash::default(); inputs.len()];
+    let mut lanes = Vec::with_capacity(LANES);
+    for (index, input) in inputs.iter().enumerate() {
+        if input.len() > CHUNK_LEN {
+            hashes[index] = hash(input);
+            continue;
+        }
+        lanes.push(index);
+        if lanes.len() == LANES {
+            hash_lane_indices(inputs, &lanes, &mut hashes);
+            lanes.clear();
+        }
+    }
+    hash_lane_indices(inputs, &lanes, &mut hashes);
+    hashes
+}
+
+fn hash_lane_indices(inputs: &[&[u8]], indices: &[usize], hashes: &mut [Hash]) {
+    let lane_inputs: Vec<&[u8]> = indices.iter().map(|&index| inputs[index]).collect();
+    let mut lane_hashes = [Hash::default(); LANES];
+    let lane_hashes = &mut lane_hashes[..indices.len()];
+    hash_lanes(&lane_inputs, lane_hashes);
+    for (&index, hash) in indices.iter().zip(lane_hashes.iter()) {
+        hashes[index] = *hash;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blake3() {
+        assert_eq!(hash(b"abc").to_bytes(), *::blake3::hash(b"abc").as_bytes());
+        assert_eq!(hashv(&[b"a", b"", b"bc"]), hash(b"abc"));
+
+        let mut hasher = Hasher::default();
+        hasher.hash(b"ab");
+        hasher.hashv(&[b"c"]);
+        assert_eq!(hasher.result(), hash(b"abc"));
+
+        let id = hash(b"id");
+        assert_eq!(extend_and_hash(&id, b"val"), hashv(&[id.as_ref(), b"val"]));
+        assert_ne!(hash(b"abc"), crate::hash::hash(b"abc"));
+    }
+
+    #[test]
+    fn test_hash_many() {
+        let data: Vec<u8> = (0..3 * CHUNK_LEN).map(|i| (i % 251) as u8).collect();
+        let lens = [0, 1, 63, 64, 65, 127, 128, 500, 1023, 1024, 1025, 2048];
+        for count in [0, 1, 7, 8, 9, 17, lens.len()] {
+            let inputs: Vec<&[u8]> = lens
+                .iter()
+                .cycle()
+                .take(count)
+                .enumerate()
+                .map(|(i, &len)| &data[i..i + len])
+                .collect();
+            let expected: Vec<_> = inputs
+                .iter()
+                .map(|input| Hash::new_from_array(*::blake3::hash(input).as_bytes()))
+                .collect();
+            assert_eq!(hash_many(&inputs), expected, "count {count}");
+        }
+
+        let inputs: Vec<&[u8]> = (0..=CHUNK_LEN + 1).map(|len| &data[..len]).collect();
+        let hashes = hash_many(&inputs);
+        for (input, hash) in inputs.iter().zip(hashes) {
+            assert_eq!(hash.to_bytes(), *::blake3::hash(input).as_bytes());
+        }
+    }
+}
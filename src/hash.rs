@@ -1,24 +1,27 @@
 //! Hashing with the [SHA-256] hash function, and a general [`Hash`] type.
 //!
+//! The [`HashFunction`] trait abstracts over the digest so that [`GenericHasher`]
+//! and the `*_with` functions can also be used with [`Blake3`] or [`Keccak256`].
+//!
 //! [SHA-256]: https://en.wikipedia.org/wiki/SHA-2
 //! [`Hash`]: struct@Hash
 
 use std::convert::TryFrom;
 use std::str::FromStr;
-use std::{fmt, mem};
+use std::{fmt, io};
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use bytemuck::{Pod, Zeroable};
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
+use crate::base58;
 use crate::sanitize::Sanitize;
 
 /// Size of a hash in bytes.
 pub const HASH_BYTES: usize = 32;
 /// Maximum string length of a base58 encoded hash.
-const MAX_BASE58_LEN: usize = 44;
+pub const MAX_BASE58_LEN: usize = 44;
 
 /// A hash; the 32-byte output of a hashing algorithm.
 ///
@@ -31,10 +34,9 @@ const MAX_BASE58_LEN: usize = 44;
 /// [`blake3`]: crate::blake3
 /// [`Message::hash`]: crate::message::Message::hash
 #[derive(
-    Serialize,
-    Deserialize,
     BorshSerialize,
     BorshDeserialize,
+    BorshSchema,
     Clone,
     Copy,
     Default,
@@ -50,12 +52,102 @@ const MAX_BASE58_LEN: usize = 44;
 #[repr(transparent)]
 pub struct Hash(pub(crate) [u8; HASH_BYTES]);
 
+impl borsh0_10::de::BorshDeserialize for Hash {
+    fn deserialize_reader<R: borsh0_10::maybestd::io::Read>(
+        reader: &mut R,
+    ) -> ::core::result::Result<Self, borsh0_10::maybestd::io::Error> {
+        Ok(Self(borsh0_10::BorshDeserialize::deserialize_reader(
+            reader,
+        )?))
+    }
+}
+impl borsh0_9::de::BorshDeserialize for Hash {
+    fn deserialize(buf: &mut &[u8]) -> ::core::result::Result<Self, borsh0_9::maybestd::io::Error> {
+        Ok(Self(borsh0_9::BorshDeserialize::deserialize(buf)?))
+    }
+}
+impl borsh0_10::ser::BorshSerialize for Hash {
+    fn serialize<W: borsh0_10::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> ::core::result::Result<(), borsh0_10::maybestd::io::Error> {
+        borsh0_10::BorshSerialize::serialize(&self.0, writer)
+    }
+}
+impl borsh0_9::ser::BorshSerialize for Hash {
+    fn serialize<W: borsh0_9::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> ::core::result::Result<(), borsh0_9::maybestd::io::Error> {
+        borsh0_9::BorshSerialize::serialize(&self.0, writer)
+    }
+}
+
+/// A hash function with a 32-byte output.
+pub trait HashFunction: Clone + Default {
+    /// Feed `val` into the hash state.
+    fn update(&mut self, val: &[u8]);
+    /// Consume the hash state and return the digest.
+    fn finalize(self) -> [u8; HASH_BYTES];
+}
+
+/// The [SHA-256] hash function.
+///
+/// [SHA-256]: https://en.wikipedia.org/wiki/SHA-2
+#[derive(Clone, Default)]
+pub struct Sha256(sha2::Sha256);
+
+impl HashFunction for Sha256 {
+    fn update(&mut self, val: &[u8]) {
+        sha2::Digest::update(&mut self.0, val);
+    }
+    fn finalize(self) -> [u8; HASH_BYTES] {
+        sha2::Digest::finalize(self.0).into()
+    }
+}
+
+/// The [blake3] hash function.
+///
+/// [blake3]: https://github.com/BLAKE3-team/BLAKE3
+#[derive(Clone, Default)]
+pub struct Blake3(blake3::Hasher);
+
+impl HashFunction for Blake3 {
+    fn update(&mut self, val: &[u8]) {
+        self.0.update(val);
+    }
+    fn finalize(self) -> [u8; HASH_BYTES] {
+        self.0.finalize().into()
+    }
+}
+
+/// The [Keccak-256] hash function, as used by Ethereum.
+///
+/// [Keccak-256]: https://keccak.team/keccak.html
+#[derive(Clone, Default)]
+pub struct Keccak256(sha3::Keccak256);
+
+impl HashFunction for Keccak256 {
+    fn update(&mut self, val: &[u8]) {
+        sha3::Digest::update(&mut self.0, val);
+    }
+    fn finalize(self) -> [u8; HASH_BYTES] {
+        sha3::Digest::finalize(self.0).into()
+    }
+}
+
+/// An incremental hasher over any [`HashFunction`].
 #[derive(Clone, Default)]
-pub struct Hasher {
-    hasher: Sha256,
+pub struct GenericHasher<H: HashFunction> {
+    hasher: H,
 }
 
-impl Hasher {
+/// An incremental [SHA-256] hasher.
+///
+/// [SHA-256]: https://en.wikipedia.org/wiki/SHA-2
+pub type Hasher = GenericHasher<Sha256>;
+
+impl<H: HashFunction> GenericHasher<H> {
     pub fn hash(&mut self, val: &[u8]) {
         self.hasher.update(val);
     }
@@ -65,10 +157,41 @@ impl Hasher {
         }
     }
     pub fn result(self) -> Hash {
-        Hash(self.hasher.finalize().into())
+        Hash(self.hasher.finalize())
+    }
+}
+
+/// Lets values be serialized straight into the hasher, e.g. with
+/// `bincode::serialize_into` or `borsh::to_writer`, without an intermediate buffer.
+impl<H: HashFunction> io::Write for GenericHasher<H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hash(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<H: HashFunction> digest::Update for GenericHasher<H> {
+    fn update(&mut self, data: &[u8]) {
+        self.hash(data);
     }
 }
 
+impl<H: HashFunction> digest::OutputSizeUser for GenericHasher<H> {
+    type OutputSize = digest::consts::U32;
+}
+
+impl<H: HashFunction> digest::FixedOutput for GenericHasher<H> {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(&self.hasher.finalize());
+    }
+}
+
+impl<H: HashFunction> digest::HashMarker for GenericHasher<H> {}
+
 impl Sanitize for Hash {}
 
 impl From<[u8; HASH_BYTES]> for Hash {
@@ -83,15 +206,40 @@ impl AsRef<[u8]> for Hash {
     }
 }
 
+impl Serialize for Hash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            let mut buf = [0u8; MAX_BASE58_LEN];
+            serializer.serialize_str(self.to_base58_buf(&mut buf))
+        } else {
+            serializer.serialize_newtype_struct("Hash", &self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct("Hash", base58::Base58Visitor::new())
+    }
+}
+
 impl fmt::Debug for Hash {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", bs58::encode(self.0).into_string())
+        let mut buf = [0u8; MAX_BASE58_LEN];
+        f.write_str(self.to_base58_buf(&mut buf))
     }
 }
 
 impl fmt::Display for Hash {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", bs58::encode(self.0).into_string())
+        let mut buf = [0u8; MAX_BASE58_LEN];
+        f.write_str(self.to_base58_buf(&mut buf))
     }
 }
 
@@ -110,14 +258,12 @@ impl FromStr for Hash {
         if s.len() > MAX_BASE58_LEN {
             return Err(ParseHashError::WrongSize);
         }
-        let bytes = bs58::decode(s)
-            .into_vec()
-            .map_err(|_| ParseHashError::Invalid)?;
-        if bytes.len() != mem::size_of::<Hash>() {
-            Err(ParseHashError::WrongSize)
-        } else {
-            Ok(Hash::new(&bytes))
-        }
+        base58::decode::<HASH_BYTES>(s.as_bytes())
+            .map(Hash)
+            .map_err(|err| match err {
+                base58::DecodeError::InvalidCharacter => ParseHashError::Invalid,
+                base58::DecodeError::WrongSize => ParseHashError::WrongSize,
+            })
     }
 }
 
@@ -144,29 +290,51 @@ impl Hash {
     pub fn to_bytes(self) -> [u8; HASH_BYTES] {
         self.0
     }
+
+    /// Base58-encode this hash into `buf` without allocating.
+    ///
+    /// Returns the encoded prefix of `buf`, which is the same string as
+    /// produced by the `Display` impl.
+    pub fn to_base58_buf<'a>(&self, buf: &'a mut [u8; MAX_BASE58_LEN]) -> &'a str {
+        let len = base58::encode(&self.0, buf);
+        std::str::from_utf8(&buf[..len]).expect("base58 alphabet is ASCII")
+    }
 }
 
 /// Return a Sha256 hash for the given data.
 pub fn hashv(vals: &[&[u8]]) -> Hash {
+    hashv_with::<Sha256>(vals)
+}
+
+/// Return a Sha256 hash for the given data.
+pub fn hash(val: &[u8]) -> Hash {
+    hashv(&[val])
+}
+
+/// Return the hash of the given hash extended with the given value.
+pub fn extend_and_hash(id: &Hash, val: &[u8]) -> Hash {
+    extend_and_hash_with::<Sha256>(id, val)
+}
+
+/// Return a hash of the given data computed with `H`.
+pub fn hashv_with<H: HashFunction>(vals: &[&[u8]]) -> Hash {
     // Perform the calculation inline, calling this from within a program is
     // not supported
     {
-        let mut hasher = Hasher::default();
+        let mut hasher = GenericHasher::<H>::default();
         hasher.hashv(vals);
         hasher.result()
     }
 }
 
-/// Return a Sha256 hash for the given data.
-pub fn hash(val: &[u8]) -> Hash {
-    hashv(&[val])
+/// Return a hash of the given data computed with `H`.
+pub fn hash_with<H: HashFunction>(val: &[u8]) -> Hash {
+    hashv_with::<H>(&[val])
 }
 
-/// Return the hash of the given hash extended with the given value.
-pub fn extend_and_hash(id: &Hash, val: &[u8]) -> Hash {
-    let mut hash_data = id.as_ref().to_vec();
-    hash_data.extend_from_slice(val);
-    hash(&hash_data)
+/// Return the `H` hash of the given hash extended with the given value.
+pub fn extend_and_hash_with<H: HashFunction>(id: &Hash, val: &[u8]) -> Hash {
+    hashv_with::<H>(&[id.as_ref(), val])
 }
 
 #[cfg(test)]
@@ -178,6 +346,139 @@ mod tests {
         assert!(Hash::new_unique() != Hash::new_unique());
     }
 
+    #[test]
+    fn test_hash_functions() {
+        assert_eq!(
+            hash(b""),
+            Hash::from_str("GKot5hBsd81kMupNCXHaqbhv3huEbxAFMLnpcX2hniwn").unwrap()
+        );
+        assert_eq!(hash_with::<Sha256>(b"abc"), hash(b"abc"));
+        assert_eq!(
+            hash_with::<Blake3>(b"abc").to_bytes(),
+            *blake3::hash(b"abc").as_bytes()
+        );
+        assert_eq!(
+            hash_with::<Keccak256>(b"").to_bytes(),
+            <[u8; HASH_BYTES]>::try_from(
+                hex::decode("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+                    .unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            hashv_with::<Keccak256>(&[b"a", b"bc"]),
+            hash_with::<Keccak256>(b"abc")
+        );
+    }
+
+    #[test]
+    fn test_hasher_write() {
+        use std::io::Write;
+
+        let value = (42u64, String::from("account state"), vec![7u8; 300]);
+
+        let mut hasher = Hasher::default();
+        bincode::serialize_into(&mut hasher, &value).unwrap();
+        assert_eq!(hasher.result(), hash(&bincode::serialize(&value).unwrap()));
+
+        let mut hasher = crate::blake3::Hasher::default();
+        borsh::to_writer(&mut hasher, &value).unwrap();
+        assert_eq!(
+            hasher.result(),
+            crate::blake3::hash(&borsh::to_vec(&value).unwrap())
+        );
+
+        let mut hasher = Hasher::default();
+        hasher.write_all(b"ab").unwrap();
+        hasher.flush().unwrap();
+        hasher.hash(b"c");
+        assert_eq!(hasher.result(), hash(b"abc"));
+    }
+
+    #[test]
+    fn test_hasher_digest() {
+        use digest::Digest;
+
+        assert_eq!(Hash::new(&Hasher::digest(b"abc")), hash(b"abc"));
+        assert_eq!(
+            Hash::new(
+                &<crate::keccak::Hasher as Digest>::new()
+                    .chain_update(b"abc")
+                    .finalize()
+            ),
+            crate::keccak::hash(b"abc")
+        );
+    }
+
+    #[test]
+    fn test_extend_and_hash() {
+        let id = hash(b"id");
+        let mut data = id.to_bytes().to_vec();
+        data.extend_from_slice(b"val");
+        assert_eq!(extend_and_hash(&id, b"val"), hash(&data));
+        assert_eq!(
+            extend_and_hash_with::<Blake3>(&id, b"val"),
+            hash_with::<Blake3>(&data)
+        );
+    }
+
+    #[test]
+    fn test_hash_to_base58_buf() {
+        let mut buf = [0u8; MAX_BASE58_LEN];
+        for hash in [
+            Hash::default(),
+            Hash::new_from_array([255u8; HASH_BYTES]),
+            hash(&[1u8]),
+        ] {
+            let encoded = bs58::encode(hash.0).into_string();
+            assert_eq!(hash.to_base58_buf(&mut buf), encoded);
+            assert_eq!(hash.to_string(), encoded);
+            assert_eq!(format!("{hash:?}"), encoded);
+        }
+    }
+
+    #[test]
+    fn test_hash_serde() {
+        let hash = hash(&[1u8]);
+
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{hash}\""));
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), hash);
+
+        // the byte array form is still accepted in human-readable formats
+        let json = serde_json::to_string(&hash.0).unwrap();
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), hash);
+
+        // binary formats are unchanged
+        let bytes = bincode::serialize(&hash).unwrap();
+        assert_eq!(bytes, hash.0);
+        assert_eq!(bincode::deserialize::<Hash>(&bytes).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_hash_borsh() {
+        let hash = hash(&[1u8]);
+
+        let bytes = borsh::to_vec(&hash).unwrap();
+        assert_eq!(bytes, hash.0);
+        assert_eq!(borsh::from_slice::<Hash>(&bytes).unwrap(), hash);
+
+        assert_eq!(borsh0_10::to_vec(&hash).unwrap(), bytes);
+        assert_eq!(
+            <Hash as borsh0_10::BorshDeserialize>::try_from_slice(&bytes).unwrap(),
+            hash
+        );
+
+        assert_eq!(borsh0_9::BorshSerialize::try_to_vec(&hash).unwrap(), bytes);
+        assert_eq!(
+            <Hash as borsh0_9::BorshDeserialize>::try_from_slice(&bytes).unwrap(),
+            hash
+        );
+
+        let container = borsh::schema::BorshSchemaContainer::for_type::<Hash>();
+        assert_eq!(container.declaration(), "Hash");
+    }
+
     #[test]
     fn test_hash_fromstr() {
         let hash = hash(&[1u8]);
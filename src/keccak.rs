@@ -0,0 +1,58 @@
+//! Hashing with the [Keccak-256] hash function, as used by Ethereum.
+//!
+//! This is the original Keccak submission, not the final SHA3-256 standard,
+//! and mirrors the SHA-256 API in [`hash`], returning the same [`Hash`] type.
+//!
+//! [Keccak-256]: https://keccak.team/keccak.html
+//! [`hash`]: crate::hash
+//! [`Hash`]: struct@Hash
+
+use crate::hash::{self, GenericHasher, Hash, Keccak256};
+
+/// An incremental Keccak-256 hasher.
+pub type Hasher = GenericHasher<Keccak256>;
+
+/// Return a Keccak-256 hash for the given data.
+pub fn hashv(vals: &[&[u8]]) -> Hash {
+    hash::hashv_with::<Keccak256>(vals)
+}
+
+/// Return a Keccak-256 hash for the given data.
+pub fn hash(val: &[u8]) -> Hash {
+    hashv(&[val])
+}
+
+/// Return the hash of the given hash extended with the given value.
+pub fn extend_and_hash(id: &Hash, val: &[u8]) -> Hash {
+    hash::extend_and_hash_with::<Keccak256>(id, val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(s: &str) -> Hash {
+        Hash::new(&hex::decode(s).unwrap())
+    }
+
+    #[test]
+    fn test_keccak() {
+        assert_eq!(
+            hash(b""),
+            from_hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            hash(b"hello world"),
+            from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad")
+        );
+        assert_eq!(hashv(&[b"hello", b" ", b"world"]), hash(b"hello world"));
+
+        let mut hasher = Hasher::default();
+        hasher.hashv(&[b"hello", b" "]);
+        hasher.hash(b"world");
+        assert_eq!(hasher.result(), hash(b"hello world"));
+
+        let id = hash(b"id");
+        assert_eq!(extend_and_hash(&id, b"val"), hashv(&[id.as_ref(), b"val"]));
+    }
+}
@@ -1,10 +1,17 @@
 pub mod account;
 pub(crate) mod atomic_u64;
+pub(crate) mod base58;
+pub mod blake3;
 pub mod clock;
 pub mod decode_error;
 pub mod hash;
 pub mod instruction;
+pub mod keccak;
 pub mod lamports;
+pub mod lt_hash;
+pub mod merkle_tree;
+pub mod poh;
+pub mod poseidon;
 pub mod pubkey;
 pub mod sanitize;
 
@@ -0,0 +1,126 @@
+//! The lattice hash ([LtHash]) used to commit to the accounts state.
+//!
+//! An [`LtHash`] is a vector of 1024 `u16` elements (2048 bytes) filled from
+//! the blake3 XOF output of its input, as in Solana. Hashes of a set of
+//! elements are combined by element-wise wrapping addition, so an element can
+//! be added to or removed from the commitment in constant time and in any
+//! order, without keeping a tree of all the elements.
+//!
+//! [LtHash]: https://eprint.iacr.org/2019/227
+
+use std::fmt;
+
+use crate::hash::Hash;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct LtHash(pub [u16; LtHash::NUM_ELEMENTS]);
+
+impl LtHash {
+    /// Number of `u16` elements, 2048 bytes in total, as in Solana's lattice
+    /// hash. A vector of 2048 elements would not match the lattice hashes
+    /// computed by Solana validators.
+    pub const NUM_ELEMENTS: usize = 1024;
+
+    /// The hash of the empty set.
+    pub const fn identity() -> Self {
+        Self([0; Self::NUM_ELEMENTS])
+    }
+
+    /// Return the lattice hash of the given data.
+    pub fn hashv(vals: &[&[u8]]) -> Self {
+        let mut hasher = ::blake3::Hasher::new();
+        for val in vals {
+            hasher.update(val);
+        }
+        let mut new = Self::identity();
+        hasher
+            .finalize_xof()
+            .fill(bytemuck::cast_slice_mut(new.0.as_mut_slice()));
+        new
+    }
+
+    /// Return the lattice hash of the given data.
+    pub fn hash(val: &[u8]) -> Self {
+        Self::hashv(&[val])
+    }
+
+    /// Add `other` to the set committed to by this hash.
+    pub fn mix_in(&mut self, other: &Self) {
+        for (lhs, rhs) in self.0.iter_mut().zip(other.0.iter()) {
+            *lhs = lhs.wrapping_add(*rhs);
+        }
+    }
+
+    /// Remove `other` from the set committed to by this hash.
+    pub fn mix_out(&mut self, other: &Self) {
+        for (lhs, rhs) in self.0.iter_mut().zip(other.0.iter()) {
+            *lhs = lhs.wrapping_sub(*rhs);
+        }
+    }
+
+    /// A short blake3 digest of this hash.
+    pub fn checksum(&self) -> Hash {
+        crate::blake3::hash(bytemuck::cast_slice(self.0.as_slice()))
+    }
+}
+
+impl Default for LtHash {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl fmt::Debug for LtHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LtHash").field(&self.checksum()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mix_in_mix_out() {
+        let a = LtHash::hash(b"a");
+        let b = LtHash::hash(b"b");
+        let c = LtHash::hashv(&[b"c", b"d"]);
+        assert_ne!(a, b);
+        assert_eq!(c, LtHash::hash(b"cd"));
+
+        let mut abc = LtHash::identity();
+        abc.mix_in(&a);
+        abc.mix_in(&b);
+        abc.mix_in(&c);
+
+        let mut cba = LtHash::default();
+        cba.mix_in(&c);
+        cba.mix_in(&b);
+        cba.mix_in(&a);
+        assert_eq!(abc, cba);
+        assert_eq!(abc.checksum(), cba.checksum());
+
+        abc.mix_out(&b);
+        let mut ac = a.clone();
+        ac.mix_in(&c);
+        assert_eq!(abc, ac);
+
+        abc.mix_out(&a);
+        abc.mix_out(&c);
+        assert_eq!(abc, LtHash::identity());
+    }
+
+    #[test]
+    fn test_hash_fills_from_xof() {
+        let mut expected = [0u8; 2 * LtHash::NUM_ELEMENTS];
+        ::blake3::Hasher::new()
+            .update(b"abc")
+            .finalize_xof()
+            .fill(&mut expected);
+        let lt_hash = LtHash::hash(b"abc");
+        for (element, bytes) in lt_hash.0.iter().zip(expected.chunks_exact(2)) {
+            assert_eq!(*element, u16::from_le_bytes([bytes[0], bytes[1]]));
+        }
+        assert_eq!(lt_hash.checksum(), crate::blake3::hash(&expected));
+    }
+}
@@ -0,0 +1,343 @@
+//! The binary merkle tree Solana builds over the transaction signatures of
+//! entries and shreds, compatible with `solana-merkle-tree`.
+//!
+//! Unlike the accounts tree of [`AccountsHasher`], leaves and intermediate
+//! nodes are hashed with distinct prefixes, and the last node of a level with
+//! an odd length is paired with itself.
+//!
+//! [`AccountsHasher`]: crate::account::account_hasher::AccountsHasher
+
+use serde::{Deserialize, Serialize};
+
+use crate::account::account_hasher::{INTERMEDIATE_PREFIX, LEAF_PREFIX};
+use crate::hash::{hashv, Hash};
+
+fn hash_leaf(item: &[u8]) -> Hash {
+    hashv(&[LEAF_PREFIX, item])
+}
+
+fn hash_intermediate(left: &Hash, right: &Hash) -> Hash {
+    hashv(&[INTERMEDIATE_PREFIX, left.as_ref(), right.as_ref()])
+}
+
+#[derive(Debug)]
+pub struct MerkleTree {
+    leaf_count: usize,
+    nodes: Vec<Hash>,
+}
+
+/// One step of a [`Proof`]: the node it produces and the sibling it is
+/// hashed with, on the left or on the right of the node below it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEntry {
+    pub target: Hash,
+    pub left_sibling: Option<Hash>,
+    pub right_sibling: Option<Hash>,
+}
+
+impl ProofEntry {
+    pub fn new(target: Hash, left_sibling: Option<Hash>, right_sibling: Option<Hash>) -> Self {
+        assert!(left_sibling.is_none() ^ right_sibling.is_none());
+        Self {
+            target,
+            left_sibling,
+            right_sibling,
+        }
+    }
+}
+
+/// Path from a leaf to the root of a [`MerkleTree`].
+///
+/// The path of the only leaf of a single leaf tree is empty, since that leaf is
+/// the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof(pub Vec<ProofEntry>);
+
+impl Proof {
+    pub fn push(&mut self, entry: ProofEntry) {
+        self.0.push(entry)
+    }
+
+    /// Verify that `leaf`, the hash of a leaf, leads up the path to `root`.
+    pub fn verify(&self, root: &Hash, leaf: Hash) -> bool {
+        let result = self.0.iter().try_fold(leaf, |candidate, entry| {
+            let hash = match (&entry.left_sibling, &entry.right_sibling) {
+                (Some(left), None) => hash_intermediate(left, &candidate),
+                (None, Some(right)) => hash_intermediate(&candidate, right),
+                _ => return None,
+            };
+            (hash == entry.target).then_some(hash)
+        });
+        result.as_ref() == Some(root)
+    }
+}
+
+impl MerkleTree {
+    #[inline]
+    fn next_level_len(level_len: usize) -> usize {
+        if level_len == 1 {
+            0
+        } else {
+            level_len.div_ceil(2)
+        }
+    }
+
+    fn calculate_vec_capacity(leaf_count: usize) -> usize {
+        // The most nodes are needed when `leaf_count - 1` leaves form a full
+        // balanced tree, and the last leaf adds a left-only path of the tree's
+        // height to the root: 2 * (n - 1) - 1 + log2(n) + 1 nodes.
+        if leaf_count > 0 {
+            leaf_count.ilog2() as usize + 2 * leaf_count + 1
+        } else {
+            0
+        }
+    }
+
+    pub fn new<T: AsRef<[u8]>>(items: &[T]) -> Self {
+        let cap = MerkleTree::calculate_vec_capacity(items.len());
+        let mut mt = MerkleTree {
+            leaf_count: items.len(),
+            nodes: Vec::with_capacity(cap),
+        };
+
+        for item in items {
+            mt.nodes.push(hash_leaf(item.as_ref()));
+        }
+
+        let mut level_len = MerkleTree::next_level_len(items.len());
+        let mut level_start = items.len();
+        let mut prev_level_len = items.len();
+        let mut prev_level_start = 0;
+        while level_len > 0 {
+            for i in 0..level_len {
+                let prev_level_idx = 2 * i;
+                let lsib = &mt.nodes[prev_level_start + prev_level_idx];
+                let rsib = if prev_level_idx + 1 < prev_level_len {
+                    &mt.nodes[prev_level_start + prev_level_idx + 1]
+                } else {
+                    // Duplicate last entry if the level length is odd
+                    &mt.nodes[prev_level_start + prev_level_idx]
+                };
+
+                let hash = hash_intermediate(lsib, rsib);
+                mt.nodes.push(hash);
+            }
+            prev_level_start = level_start;
+            prev_level_len = level_len;
+            level_start += level_len;
+            level_len = MerkleTree::next_level_len(level_len);
+        }
+
+        mt
+    }
+
+    pub fn get_root(&self) -> Option<&Hash> {
+        self.nodes.iter().last()
+    }
+
+    /// The proof that the leaf at `index` is part of the tree.
+    pub fn find_path(&self, index: usize) -> Option<Proof> {
+        if index >= self.leaf_count {
+            return None;
+        }
+
+        let mut level_len = self.leaf_count;
+        let mut level_start = 0;
+        let mut path = Proof::default();
+        let mut node_index = index;
+        let mut lsib = None;
+        let mut rsib = None;
+        while level_len > 0 {
+            let level = &self.nodes[level_start..(level_start + level_len)];
+
+            let target = level[node_index];
+            if lsib.is_some() || rsib.is_some() {
+                path.push(ProofEntry::new(target, lsib, rsib));
+            }
+            if node_index.is_multiple_of(2) {
+                lsib = None;
+                rsib = if node_index + 1 < level.len() {
+                    Some(level[node_index + 1])
+                } else {
+                    Some(level[node_index])
+                };
+            } else {
+                lsib = Some(level[node_index - 1]);
+                rsib = None;
+            }
+            node_index /= 2;
+
+            level_start += level_len;
+            level_len = MerkleTree::next_level_len(level_len);
+        }
+        Some(path)
+    }
+
+    /// Hash of the leaf for `item`, to be passed to [`Proof::verify`].
+    pub fn hash_leaf(item: &[u8]) -> Hash {
+        hash_leaf(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &[&[u8]] = &[
+        b"my", b"very", b"eager", b"mother", b"just", b"served", b"us", b"nine", b"pizzas",
+        b"make", b"prime",
+    ];
+    const BAD: &[&[u8]] = &[b"bad", b"missing", b"false"];
+
+    #[test]
+    fn test_tree_from_empty() {
+        let mt = MerkleTree::new::<[u8; 0]>(&[]);
+        assert_eq!(mt.get_root(), None);
+    }
+
+    #[test]
+    fn test_tree_from_one() {
+        let input = b"test";
+        let mt = MerkleTree::new(&[input]);
+        let expected = hash_leaf(input);
+        assert_eq!(mt.get_root(), Some(&expected));
+    }
+
+    #[test]
+    fn test_tree_from_many() {
+        let mt = MerkleTree::new(TEST);
+        // This golden hash will need to be updated whenever the contents of `TEST` change in any
+        // way, including addition, removal and reordering or any of the tree calculation algo
+        // changes
+        let bytes = hex::decode("b40c847546fdceea166f927fc46c5ca33c3638236a36275c1346d3dffb84e1bc")
+            .unwrap();
+        let expected = Hash::new(&bytes);
+        assert_eq!(mt.get_root(), Some(&expected));
+    }
+
+    #[test]
+    fn test_path_creation() {
+        let mt = MerkleTree::new(TEST);
+        for (i, _s) in TEST.iter().enumerate() {
+            let _path = mt.find_path(i).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_path_creation_bad_index() {
+        let mt = MerkleTree::new(TEST);
+        assert_eq!(mt.find_path(TEST.len()), None);
+    }
+
+    #[test]
+    fn test_path_verify_good() {
+        let mt = MerkleTree::new(TEST);
+        let root = mt.get_root().unwrap();
+        for (i, s) in TEST.iter().enumerate() {
+            let hash = MerkleTree::hash_leaf(s);
+            let path = mt.find_path(i).unwrap();
+            assert!(path.verify(root, hash));
+        }
+    }
+
+    #[test]
+    fn test_path_verify_bad() {
+        let mt = MerkleTree::new(TEST);
+        let root = mt.get_root().unwrap();
+        for (i, s) in BAD.iter().enumerate() {
+            let hash = MerkleTree::hash_leaf(s);
+            let path = mt.find_path(i).unwrap();
+            assert!(!path.verify(root, hash));
+        }
+    }
+
+    #[test]
+    fn test_path_verify_wrong_root() {
+        let mt = MerkleTree::new(TEST);
+        let other = MerkleTree::new(&TEST[1..]);
+        let wrong_root = other.get_root().unwrap();
+        for (i, s) in TEST.iter().enumerate() {
+            let hash = MerkleTree::hash_leaf(s);
+            let path = mt.find_path(i).unwrap();
+            assert!(!path.verify(wrong_root, hash));
+        }
+        // an empty path only proves the root itself
+        let hash = MerkleTree::hash_leaf(TEST[0]);
+        assert!(!Proof::default().verify(&Hash::new_unique(), hash));
+        assert!(Proof::default().verify(&hash, hash));
+    }
+
+    #[test]
+    fn test_path_verify_single_leaf() {
+        let mt = MerkleTree::new(&[b"test"]);
+        let root = mt.get_root().unwrap();
+        let path = mt.find_path(0).unwrap();
+        assert_eq!(path, Proof::default());
+        assert!(path.verify(root, MerkleTree::hash_leaf(b"test")));
+        assert!(!path.verify(root, MerkleTree::hash_leaf(b"bad")));
+    }
+
+    #[test]
+    fn test_path_serialize() {
+        let mt = MerkleTree::new(TEST);
+        let root = mt.get_root().unwrap();
+        let path = mt.find_path(3).unwrap();
+        let bytes = bincode::serialize(&path).unwrap();
+        let path: Proof = bincode::deserialize(&bytes).unwrap();
+        assert!(path.verify(root, MerkleTree::hash_leaf(TEST[3])));
+    }
+
+    #[test]
+    fn test_path_verify_malformed_entry() {
+        let mt = MerkleTree::new(TEST);
+        let root = mt.get_root().unwrap();
+        let mut path = mt.find_path(0).unwrap();
+        path.0[0].left_sibling = path.0[0].right_sibling;
+        assert!(!path.verify(root, MerkleTree::hash_leaf(TEST[0])));
+    }
+
+    #[test]
+    fn test_proof_entry_instantiation_lsib_set() {
+        ProofEntry::new(Hash::default(), Some(Hash::default()), None);
+    }
+
+    #[test]
+    fn test_proof_entry_instantiation_rsib_set() {
+        ProofEntry::new(Hash::default(), None, Some(Hash::default()));
+    }
+
+    #[test]
+    fn test_nodes_capacity_compute() {
+        let iteration_count = |mut leaf_count: usize| -> usize {
+            let mut capacity = 0;
+            while leaf_count > 0 {
+                capacity += leaf_count;
+                leaf_count = MerkleTree::next_level_len(leaf_count);
+            }
+            capacity
+        };
+
+        // test max 64k leaf nodes compute
+        for leaf_count in 0..65536 {
+            let math_count = MerkleTree::calculate_vec_capacity(leaf_count);
+            let iter_count = iteration_count(leaf_count);
+            assert!(math_count >= iter_count);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_proof_entry_instantiation_both_clear() {
+        ProofEntry::new(Hash::default(), None, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_proof_entry_instantiation_both_set() {
+        ProofEntry::new(
+            Hash::default(),
+            Some(Hash::default()),
+            Some(Hash::default()),
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! Proof of History: a SHA-256 hash chain that proves the passage of time.
+//!
+//! A [`Poh`] recorder repeatedly hashes its state, periodically emitting ticks
+//! and mixing in the hashes of recorded transactions. The resulting [`Entry`]
+//! chain can be checked by anyone with [`verify_entries`], which recomputes
+//! every hash from the starting hash.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hash::{extend_and_hash, hash, hashv, Hash};
+
+/// Number of hashes per tick used when the recorder is not ticking at a fixed
+/// rate; ticks are then only produced on demand.
+pub const LOW_POWER_MODE: u64 = u64::MAX;
+
+/// A PoH recorder.
+pub struct Poh {
+    pub hash: Hash,
+    num_hashes: u64,
+    hashes_per_tick: u64,
+    remaining_hashes: u64,
+    tick_number: u64,
+}
+
+/// The hash and number of hashes produced by a call to [`Poh::record`] or
+/// [`Poh::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PohEntry {
+    pub num_hashes: u64,
+    pub hash: Hash,
+}
+
+impl Poh {
+    /// Create a recorder starting from `hash`.
+    ///
+    /// With `hashes_per_tick` of `None` the recorder runs in
+    /// [`LOW_POWER_MODE`] and every call to [`tick`] produces a tick.
+    ///
+    /// [`tick`]: Poh::tick
+    pub fn new(hash: Hash, hashes_per_tick: Option<u64>) -> Self {
+        Self::new_with_tick_number(hash, hashes_per_tick, 0)
+    }
+
+    pub fn new_with_tick_number(
+        hash: Hash,
+        hashes_per_tick: Option<u64>,
+        tick_number: u64,
+    ) -> Self {
+        let hashes_per_tick = hashes_per_tick.unwrap_or(LOW_POWER_MODE);
+        assert!(hashes_per_tick > 1);
+        Poh {
+            hash,
+            num_hashes: 0,
+            hashes_per_tick,
+            remaining_hashes: hashes_per_tick,
+            tick_number,
+        }
+    }
+
+    /// Reset the recorder to `hash`, discarding any hashes since the last tick.
+    pub fn reset(&mut self, hash: Hash, hashes_per_tick: Option<u64>) {
+        *self = Poh::new(hash, hashes_per_tick);
+    }
+
+    pub fn hashes_per_tick(&self) -> u64 {
+        self.hashes_per_tick
+    }
+
+    pub fn tick_number(&self) -> u64 {
+        self.tick_number
+    }
+
+    /// Return `true` if the caller needs to [`tick`] next.
+    ///
+    /// Hashes at most `max_num_hashes` times, always leaving one hash for the
+    /// tick itself.
+    ///
+    /// [`tick`]: Poh::tick
+    pub fn hash(&mut self, max_num_hashes: u64) -> bool {
+        let num_hashes = std::cmp::min(self.remaining_hashes - 1, max_num_hashes);
+
+        for _ in 0..num_hashes {
+            self.hash = hash(self.hash.as_ref());
+        }
+        self.num_hashes += num_hashes;
+        self.remaining_hashes -= num_hashes;
+
+        assert!(self.remaining_hashes > 0);
+        self.remaining_hashes == 1 // Return `true` if caller needs to `tick()` next
+    }
+
+    /// Mix `mixin` into the chain.
+    ///
+    /// Returns `None` if the recorder needs to [`tick`] first.
+    ///
+    /// [`tick`]: Poh::tick
+    pub fn record(&mut self, mixin: Hash) -> Option<PohEntry> {
+        if self.remaining_hashes == 1 {
+            return None; // Caller needs to `tick()` first
+        }
+
+        self.hash = extend_and_hash(&self.hash, mixin.as_ref());
+        let num_hashes = self.num_hashes + 1;
+        self.num_hashes = 0;
+        self.remaining_hashes -= 1;
+
+        Some(PohEntry {
+            num_hashes,
+            hash: self.hash,
+        })
+    }
+
+    /// Hash once more and, if that completes a tick, return it.
+    pub fn tick(&mut self) -> Option<PohEntry> {
+        self.hash = hash(self.hash.as_ref());
+        self.num_hashes += 1;
+        self.remaining_hashes -= 1;
+
+        // If we are in low power mode then always generate a tick.
+        // Otherwise only tick if there are no remaining hashes
+        if self.hashes_per_tick != LOW_POWER_MODE && self.remaining_hashes != 0 {
+            return None;
+        }
+
+        let num_hashes = self.num_hashes;
+        self.remaining_hashes = self.hashes_per_tick;
+        self.num_hashes = 0;
+        self.tick_number += 1;
+        Some(PohEntry {
+            num_hashes,
+            hash: self.hash,
+        })
+    }
+}
+
+/// A segment of the PoH chain.
+///
+/// An entry with no transactions is a tick. Otherwise, the hashes of its
+/// transactions were mixed into the chain by the entry's final hash.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct Entry {
+    /// The number of hashes since the previous entry.
+    pub num_hashes: u64,
+    /// The hash `num_hashes` after the previous entry's hash.
+    pub hash: Hash,
+    /// Hashes of the transactions observed before `hash` was generated.
+    pub transactions: Vec<Hash>,
+}
+
+impl Entry {
+    /// Create the entry that follows `prev_hash` after `num_hashes` hashes,
+    /// mixing in `transactions` if there are any.
+    pub fn new(prev_hash: &Hash, num_hashes: u64, transactions: Vec<Hash>) -> Self {
+        // If you passed in transactions, but passed in num_hashes == 0, then
+        // next_hash will generate the next hash and set num_hashes == 1
+        let num_hashes = if num_hashes == 0 && !transactions.is_empty() {
+            1
+        } else {
+            num_hashes
+        };
+        let hash = next_hash(prev_hash, num_hashes, &transactions);
+        Entry {
+            num_hashes,
+            hash,
+            transactions,
+        }
+    }
+
+    pub fn new_tick(num_hashes: u64, hash: &Hash) -> Self {
+        Entry {
+            num_hashes,
+            hash: *hash,
+            transactions: vec![],
+        }
+    }
+
+    /// Verify that this entry follows `start_hash`.
+    pub fn verify(&self, start_hash: &Hash) -> bool {
+        let ref_hash = next_hash(start_hash, self.num_hashes, &self.transactions);
+        if self.hash != ref_hash {
+            log::warn!(
+                "next_hash is invalid expected: {:?} actual: {:?}",
+                self.hash,
+                ref_hash
+            );
+            return false;
+        }
+        true
+    }
+
+    pub fn is_tick(&self) -> bool {
+        self.transactions.is_empty()
+    }
+}
+
+/// Return the hash that commits to `transactions`, or the default hash if
+/// there are none.
+pub fn hash_transactions(transactions: &[Hash]) -> Hash {
+    if transactions.is_empty() {
+        return Hash::default();
+    }
+    let transactions: Vec<&[u8]> = transactions.iter().map(AsRef::as_ref).collect();
+    hashv(&transactions)
+}
+
+/// Create the hash `num_hashes` after `start_hash`.
+///
+/// If `transactions` is not empty, their hash is mixed in as the final hash;
+/// otherwise the final hash is a tick.
+pub fn next_hash(start_hash: &Hash, num_hashes: u64, transactions: &[Hash]) -> Hash {
+    if num_hashes == 0 && transactions.is_empty() {
+        return *start_hash;
+    }
+
+    let mut poh = Poh::new(*start_hash, None);
+    poh.hash(num_hashes.saturating_sub(1));
+    if transactions.is_empty() {
+        poh.tick().unwrap().hash
+    } else {
+        poh.record(hash_transactions(transactions)).unwrap().hash
+    }
+}
+
+/// Create `num_ticks` tick entries, `num_hashes` apart, following `hash`.
+pub fn create_ticks(num_ticks: u64, num_hashes: u64, mut hash: Hash) -> Vec<Entry> {
+    let mut ticks = Vec::with_capacity(num_ticks as usize);
+    for _ in 0..num_ticks {
+        let new_tick = Entry::new(&hash, num_hashes, vec![]);
+        hash = new_tick.hash;
+        ticks.push(new_tick);
+    }
+    ticks
+}
+
+/// Verify that `entries` form a chain starting at `start_hash`.
+pub fn verify_entries(start_hash: &Hash, entries: &[Entry]) -> bool {
+    let mut prev_hash = start_hash;
+    for entry in entries {
+        if !entry.verify(prev_hash) {
+            return false;
+        }
+        prev_hash = &entry.hash;
+    }
+    true
+}
+
+/// Verify that `entries` form a chain starting at `start_hash`, checking
+/// entries in parallel.
+///
+/// Every entry only depends on the hash of the previous one, so the chain can
+/// be split into independent segments.
+#[cfg(feature = "rayon")]
+pub fn verify_entries_par(start_hash: &Hash, entries: &[Entry]) -> bool {
+    use rayon::prelude::*;
+
+    entries.par_iter().enumerate().all(|(i, entry)| {
+        let prev_hash = if i == 0 {
+            start_hash
+        } else {
+            &entries[i - 1].hash
+        };
+        entry.verify(prev_hash)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poh_tick() {
+        let mut poh = Poh::new(Hash::default(), Some(2));
+        assert_eq!(poh.remaining_hashes, 2);
+        assert!(poh.tick().is_none());
+        assert_eq!(poh.remaining_hashes, 1);
+        assert!(matches!(poh.tick(), Some(PohEntry { num_hashes: 2, .. })));
+        assert_eq!(poh.remaining_hashes, 2); // Ready for the next tick
+        assert_eq!(poh.tick_number(), 1);
+    }
+
+    #[test]
+    fn test_poh_tick_large_batch() {
+        let mut poh = Poh::new(Hash::default(), Some(2));
+        assert_eq!(poh.remaining_hashes, 2);
+        assert!(poh.hash(1_000_000)); // Stop hashing before the next tick
+        assert_eq!(poh.remaining_hashes, 1);
+        assert!(poh.hash(1_000_000)); // Does nothing...
+        assert_eq!(poh.remaining_hashes, 1);
+        poh.tick();
+        assert_eq!(poh.remaining_hashes, 2); // Ready for the next tick
+    }
+
+    #[test]
+    fn test_poh_record_not_permitted_at_final_hash() {
+        let mut poh = Poh::new(Hash::default(), Some(10));
+        assert!(poh.hash(9));
+        assert_eq!(poh.remaining_hashes, 1);
+        assert!(poh.record(Hash::default()).is_none()); // <-- record() rejected to avoid exceeding hashes_per_tick
+        assert_matches_tick(poh.tick(), 10);
+        assert!(poh.record(Hash::default()).is_some()); // <-- record() ok
+        assert_eq!(poh.remaining_hashes, 9);
+    }
+
+    fn assert_matches_tick(entry: Option<PohEntry>, num_hashes: u64) {
+        assert_eq!(entry.map(|entry| entry.num_hashes), Some(num_hashes));
+    }
+
+    #[test]
+    fn test_poh_chain() {
+        let zero = Hash::default();
+        let one = hash(zero.as_ref());
+        let two = hash(one.as_ref());
+        let one_with_zero = hashv(&[zero.as_ref(), zero.as_ref()]);
+
+        let mut poh = Poh::new(zero, None);
+        assert_eq!(
+            poh.tick(),
+            Some(PohEntry {
+                num_hashes: 1,
+                hash: one
+            })
+        );
+        assert_eq!(
+            poh.tick(),
+            Some(PohEntry {
+                num_hashes: 1,
+                hash: two
+            })
+        );
+
+        let mut poh = Poh::new(zero, None);
+        assert_eq!(
+            poh.record(zero),
+            Some(PohEntry {
+                num_hashes: 1,
+                hash: one_with_zero
+            })
+        );
+
+        assert_eq!(next_hash(&zero, 0, &[]), zero);
+        assert_eq!(next_hash(&zero, 1, &[]), one);
+        assert_eq!(next_hash(&zero, 2, &[]), two);
+        assert_eq!(
+            next_hash(&zero, 1, &[zero]),
+            hashv(&[zero.as_ref(), hash(zero.as_ref()).as_ref()])
+        );
+    }
+
+    #[test]
+    fn test_entry_verify() {
+        let zero = Hash::default();
+        let one = hash(zero.as_ref());
+        assert!(Entry::new_tick(0, &zero).verify(&zero)); // base case, never used
+        assert!(!Entry::new_tick(0, &zero).verify(&one)); // base case, bad
+        assert!(Entry::new(&zero, 1, vec![]).verify(&zero)); // inductive step
+        assert!(!Entry::new(&zero, 1, vec![]).verify(&one)); // inductive step, bad
+
+        let tx0 = hash(b"tx0");
+        let tx1 = hash(b"tx1");
+        let mut entry = Entry::new(&one, 0, vec![tx0, tx1]);
+        assert_eq!(entry.num_hashes, 1);
+        assert!(entry.verify(&one));
+        assert!(!entry.is_tick());
+
+        // reordering transactions changes the entry hash
+        entry.transactions.swap(0, 1);
+        assert!(!entry.verify(&one));
+    }
+
+    #[test]
+    fn test_verify_entries() {
+        let zero = Hash::default();
+        let one = hash(zero.as_ref());
+
+        assert!(verify_entries(&zero, &[])); // base case
+        assert!(verify_entries(&zero, &[Entry::new_tick(0, &zero)])); // singleton case 1
+        assert!(!verify_entries(&zero, &[Entry::new_tick(0, &one)])); // singleton case 2, bad
+
+        let mut entries = create_ticks(10, 3, zero);
+        let last_hash = entries.last().unwrap().hash;
+        entries.push(Entry::new(&last_hash, 5, vec![hash(b"tx")]));
+        entries.extend(create_ticks(3, 7, entries.last().unwrap().hash));
+        assert!(verify_entries(&zero, &entries));
+        assert!(!verify_entries(&one, &entries));
+
+        // recording with a Poh yields the same chain
+        let mut poh = Poh::new(zero, Some(4));
+        let mut recorded = vec![];
+        for _ in 0..3 {
+            poh.hash(u64::MAX);
+            let tick = poh.tick().unwrap();
+            recorded.push(Entry::new_tick(tick.num_hashes, &tick.hash));
+        }
+        poh.hash(1);
+        let mixin = hash_transactions(&[hash(b"tx")]);
+        let record = poh.record(mixin).unwrap();
+        recorded.push(Entry {
+            num_hashes: record.num_hashes,
+            hash: record.hash,
+            transactions: vec![hash(b"tx")],
+        });
+        assert!(verify_entries(&zero, &recorded));
+
+        recorded[1].num_hashes += 1;
+        assert!(!verify_entries(&zero, &recorded));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_verify_entries_par() {
+        let zero = Hash::default();
+        let mut entries = create_ticks(100, 10, zero);
+        assert!(verify_entries_par(&zero, &entries));
+        assert!(verify_entries_par(&zero, &[]));
+
+        entries[50].hash = Hash::new_unique();
+        assert!(!verify_entries_par(&zero, &entries));
+        assert!(!verify_entries_par(&hash(zero.as_ref()), &entries[..1]));
+    }
+}
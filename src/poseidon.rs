@@ -0,0 +1,295 @@
+//! Hashing with the [Poseidon] hash function over the BN254 scalar field.
+//!
+//! Compatible with the `sol_poseidon` syscall: inputs are field elements of up
+//! to 32 bytes in either big or little endian, and the circom-compatible x5
+//! parameters are used. Unlike the SHA-256 and blake3 hashes in [`hash`] and
+//! [`blake3`], Poseidon is cheap to arithmetize, which makes it suitable for
+//! commitments that are verified inside a zk circuit.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458
+//! [`hash`]: crate::hash
+//! [`blake3`]: crate::blake3
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonBytesHasher, PoseidonError};
+use thiserror::Error;
+
+use crate::hash::Hash;
+
+/// Length of Poseidon hash result.
+pub const HASH_BYTES: usize = 32;
+/// Maximum number of inputs accepted by the `sol_poseidon` syscall.
+pub const MAX_INPUTS: usize = 12;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PoseidonSyscallError {
+    #[error("Invalid parameters.")]
+    InvalidParameters,
+    #[error("Invalid endianness.")]
+    InvalidEndianness,
+    #[error("Invalid number of inputs. Maximum allowed is 12.")]
+    InvalidNumberOfInputs,
+    #[error("Input is an empty slice.")]
+    EmptyInput,
+    #[error(
+        "Invalid length of the input. The length matching the modulus of the prime field is 32."
+    )]
+    InvalidInputLength,
+    #[error("Failed to convert bytes into a prime field element.")]
+    BytesToPrimeFieldElement,
+    #[error("Input is larger than the modulus of the prime field.")]
+    InputLargerThanModulus,
+    #[error("Failed to convert a vector of bytes into an array.")]
+    VecToArray,
+    #[error("Failed to convert the number of inputs from u64 to u8.")]
+    U64Tou8,
+    #[error("Failed to convert bytes to BigInt")]
+    BytesToBigInt,
+    #[error("Invalid width. Choose a width between 2 and 16 for 1 to 15 inputs.")]
+    InvalidWidthCircom,
+    #[error("Unexpected error")]
+    Unexpected,
+}
+
+impl From<u64> for PoseidonSyscallError {
+    fn from(error: u64) -> Self {
+        match error {
+            1 => PoseidonSyscallError::InvalidParameters,
+            2 => PoseidonSyscallError::InvalidEndianness,
+            3 => PoseidonSyscallError::InvalidNumberOfInputs,
+            4 => PoseidonSyscallError::EmptyInput,
+            5 => PoseidonSyscallError::InvalidInputLength,
+            6 => PoseidonSyscallError::BytesToPrimeFieldElement,
+            7 => PoseidonSyscallError::InputLargerThanModulus,
+            8 => PoseidonSyscallError::VecToArray,
+            9 => PoseidonSyscallError::U64Tou8,
+            10 => PoseidonSyscallError::BytesToBigInt,
+            11 => PoseidonSyscallError::InvalidWidthCircom,
+            _ => PoseidonSyscallError::Unexpected,
+        }
+    }
+}
+
+impl From<PoseidonSyscallError> for u64 {
+    fn from(error: PoseidonSyscallError) -> Self {
+        match error {
+            PoseidonSyscallError::InvalidParameters => 1,
+            PoseidonSyscallError::InvalidEndianness => 2,
+            PoseidonSyscallError::InvalidNumberOfInputs => 3,
+            PoseidonSyscallError::EmptyInput => 4,
+            PoseidonSyscallError::InvalidInputLength => 5,
+            PoseidonSyscallError::BytesToPrimeFieldElement => 6,
+            PoseidonSyscallError::InputLargerThanModulus => 7,
+            PoseidonSyscallError::VecToArray => 8,
+            PoseidonSyscallError::U64Tou8 => 9,
+            PoseidonSyscallError::BytesToBigInt => 10,
+            PoseidonSyscallError::InvalidWidthCircom => 11,
+            PoseidonSyscallError::Unexpected => 12,
+        }
+    }
+}
+
+impl From<PoseidonError> for PoseidonSyscallError {
+    fn from(error: PoseidonError) -> Self {
+        match error {
+            PoseidonError::InvalidNumberOfInputs { .. } => {
+                PoseidonSyscallError::InvalidNumberOfInputs
+            }
+            PoseidonError::EmptyInput => PoseidonSyscallError::EmptyInput,
+            PoseidonError::InvalidInputLength { .. } => PoseidonSyscallError::InvalidInputLength,
+            PoseidonError::BytesToPrimeFieldElement { .. } => {
+                PoseidonSyscallError::BytesToPrimeFieldElement
+            }
+            PoseidonError::InputLargerThanModulus => PoseidonSyscallError::InputLargerThanModulus,
+            PoseidonError::VecToArray => PoseidonSyscallError::VecToArray,
+            PoseidonError::U64Tou8 => PoseidonSyscallError::U64Tou8,
+            PoseidonError::BytesToBigInt => PoseidonSyscallError::BytesToBigInt,
+            PoseidonError::InvalidWidthCircom { .. } => PoseidonSyscallError::InvalidWidthCircom,
+        }
+    }
+}
+
+/// Configuration parameters for the Poseidon hash function.
+///
+/// The discriminants are the values passed to the `sol_poseidon` syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u64)]
+pub enum Parameters {
+    /// BN254 curve, x^5 S-boxes, circom-compatible round constants and MDS
+    /// matrices, for up to 12 inputs.
+    Bn254X5 = 0,
+}
+
+impl TryFrom<u64> for Parameters {
+    type Error = PoseidonSyscallError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            x if x == Parameters::Bn254X5 as u64 => Ok(Parameters::Bn254X5),
+            _ => Err(PoseidonSyscallError::InvalidParameters),
+        }
+    }
+}
+
+/// Byte order of the inputs and of the output.
+///
+/// The discriminants are the values passed to the `sol_poseidon` syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u64)]
+pub enum Endianness {
+    BigEndian = 0,
+    LittleEndian,
+}
+
+impl TryFrom<u64> for Endianness {
+    type Error = PoseidonSyscallError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            x if x == Endianness::BigEndian as u64 => Ok(Endianness::BigEndian),
+            x if x == Endianness::LittleEndian as u64 => Ok(Endianness::LittleEndian),
+            _ => Err(PoseidonSyscallError::InvalidEndianness),
+        }
+    }
+}
+
+/// Return a Poseidon hash for the given data with the given parameters and
+/// endianness.
+///
+/// Each element of `vals` is a field element of at most 32 bytes and must be
+/// smaller than the modulus of the BN254 scalar field.
+pub fn hashv(
+    parameters: Parameters,
+    endianness: Endianness,
+    vals: &[&[u8]],
+) -> Result<Hash, PoseidonSyscallError> {
+    let Parameters::Bn254X5 = parameters;
+    if vals.is_empty() {
+        return Err(PoseidonSyscallError::EmptyInput);
+    }
+    if vals.len() > MAX_INPUTS {
+        return Err(PoseidonSyscallError::InvalidNumberOfInputs);
+    }
+
+    let mut hasher = Poseidon::<Fr>::new_circom(vals.len())?;
+    let result = match endianness {
+        Endianness::BigEndian => hasher.hash_bytes_be(vals),
+        Endianness::LittleEndian => hasher.hash_bytes_le(vals),
+    }?;
+    Ok(Hash::new_from_array(result))
+}
+
+/// Return a Poseidon hash for the given data with the given parameters and
+/// endianness.
+pub fn hash(
+    parameters: Parameters,
+    endianness: Endianness,
+    val: &[u8],
+) -> Result<Hash, PoseidonSyscallError> {
+    hashv(parameters, endianness, &[val])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poseidon_input_ones_twos() {
+        let expected_be = [
+            13, 84, 225, 147, 143, 138, 140, 28, 125, 235, 94, 3, 85, 242, 99, 25, 32, 123, 132,
+            254, 156, 162, 206, 27, 38, 231, 53, 200, 41, 130, 25, 144,
+        ];
+        let hash = hashv(
+            Parameters::Bn254X5,
+            Endianness::BigEndian,
+            &[&[1u8; 32], &[2u8; 32]],
+        )
+        .unwrap();
+        assert_eq!(hash.to_bytes(), expected_be);
+
+        let mut expected_le = expected_be;
+        expected_le.reverse();
+        let hash = hashv(
+            Parameters::Bn254X5,
+            Endianness::LittleEndian,
+            &[&[1u8; 32], &[2u8; 32]],
+        )
+        .unwrap();
+        assert_eq!(hash.to_bytes(), expected_le);
+    }
+
+    #[test]
+    fn test_poseidon_input_one_two() {
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        let mut two = [0u8; 32];
+        two[0] = 2;
+        let hash = hashv(Parameters::Bn254X5, Endianness::LittleEndian, &[&one, &two]).unwrap();
+        assert_eq!(
+            hash.to_bytes(),
+            [
+                154, 24, 23, 68, 122, 96, 25, 158, 81, 69, 50, 116, 242, 23, 54, 42, 207, 233, 98,
+                150, 107, 76, 246, 61, 65, 144, 214, 231, 245, 192, 92, 17
+            ]
+        );
+
+        one.reverse();
+        two.reverse();
+        let hash_be = hashv(Parameters::Bn254X5, Endianness::BigEndian, &[&one, &two]).unwrap();
+        let mut hash_be = hash_be.to_bytes();
+        hash_be.reverse();
+        assert_eq!(hash_be, hash.to_bytes());
+    }
+
+    #[test]
+    fn test_poseidon_errors() {
+        assert_eq!(
+            hashv(Parameters::Bn254X5, Endianness::BigEndian, &[]),
+            Err(PoseidonSyscallError::EmptyInput)
+        );
+        assert_eq!(
+            hash(Parameters::Bn254X5, Endianness::BigEndian, &[]),
+            Err(PoseidonSyscallError::EmptyInput)
+        );
+        assert_eq!(
+            hash(Parameters::Bn254X5, Endianness::BigEndian, &[1u8; 33]),
+            Err(PoseidonSyscallError::InvalidInputLength)
+        );
+        assert_eq!(
+            hash(Parameters::Bn254X5, Endianness::BigEndian, &[255u8; 32]),
+            Err(PoseidonSyscallError::InputLargerThanModulus)
+        );
+        let input: &[u8] = &[1u8; 32];
+        assert!(hashv(
+            Parameters::Bn254X5,
+            Endianness::BigEndian,
+            &[input; MAX_INPUTS]
+        )
+        .is_ok());
+        assert_eq!(
+            hashv(
+                Parameters::Bn254X5,
+                Endianness::BigEndian,
+                &[input; MAX_INPUTS + 1]
+            ),
+            Err(PoseidonSyscallError::InvalidNumberOfInputs)
+        );
+    }
+
+    #[test]
+    fn test_poseidon_syscall_codes() {
+        assert_eq!(Parameters::try_from(0), Ok(Parameters::Bn254X5));
+        assert_eq!(
+            Parameters::try_from(1),
+            Err(PoseidonSyscallError::InvalidParameters)
+        );
+        assert_eq!(Endianness::try_from(1), Ok(Endianness::LittleEndian));
+        assert_eq!(
+            Endianness::try_from(2),
+            Err(PoseidonSyscallError::InvalidEndianness)
+        );
+        for code in 1..=12 {
+            assert_eq!(u64::from(PoseidonSyscallError::from(code)), code);
+        }
+    }
+}
@@ -2,15 +2,16 @@
 
 #![allow(clippy::arithmetic_side_effects)]
 use std::convert::{Infallible, TryFrom};
+use std::fmt;
 use std::str::FromStr;
-use std::{fmt, mem};
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use bytemuck::{Pod, Zeroable};
 use num_derive::{FromPrimitive, ToPrimitive};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
+use crate::base58;
 use crate::decode_error::DecodeError;
 
 /// Number of bytes in a pubkey
@@ -20,7 +21,9 @@ pub const MAX_SEED_LEN: usize = 32;
 /// Maximum number of seeds
 pub const MAX_SEEDS: usize = 16;
 /// Maximum string length of a base58 encoded pubkey
-const MAX_BASE58_LEN: usize = 44;
+pub const MAX_BASE58_LEN: usize = 44;
+
+const PDA_MARKER: &[u8; 21] = b"ProgramDerivedAddress";
 
 #[derive(Error, Debug, Serialize, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum PubkeyError {
@@ -42,6 +45,7 @@ impl From<u64> for PubkeyError {
         match error {
             0 => PubkeyError::MaxSeedLengthExceeded,
             1 => PubkeyError::InvalidSeeds,
+            2 => PubkeyError::IllegalOwner,
             _ => panic!("Unsupported PubkeyError"),
         }
     }
@@ -64,18 +68,17 @@ impl From<u64> for PubkeyError {
 #[repr(transparent)]
 #[derive(
     BorshDeserialize,
+    BorshSchema,
     BorshSerialize,
     Clone,
     Copy,
     Default,
-    Deserialize,
     Eq,
     Hash,
     Ord,
     PartialEq,
     PartialOrd,
     Pod,
-    Serialize,
     Zeroable,
 )]
 #[borsh(crate = "borsh")]
@@ -110,14 +113,12 @@ impl FromStr for Pubkey {
         if s.len() > MAX_BASE58_LEN {
             return Err(ParsePubkeyError::WrongSize);
         }
-        let pubkey_vec = bs58::decode(s)
-            .into_vec()
-            .map_err(|_| ParsePubkeyError::Invalid)?;
-        if pubkey_vec.len() != mem::size_of::<Pubkey>() {
-            Err(ParsePubkeyError::WrongSize)
-        } else {
-            Pubkey::try_from(pubkey_vec).map_err(|_| ParsePubkeyError::Invalid)
-        }
+        base58::decode::<PUBKEY_BYTES>(s.as_bytes())
+            .map(Pubkey)
+            .map_err(|err| match err {
+                base58::DecodeError::InvalidCharacter => ParsePubkeyError::Invalid,
+                base58::DecodeError::WrongSize => ParsePubkeyError::WrongSize,
+            })
     }
 }
 
@@ -165,15 +166,40 @@ impl AsMut<[u8]> for Pubkey {
     }
 }
 
+impl Serialize for Pubkey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            let mut buf = [0u8; MAX_BASE58_LEN];
+            serializer.serialize_str(self.to_base58_buf(&mut buf))
+        } else {
+            serializer.serialize_newtype_struct("Pubkey", &self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Pubkey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct("Pubkey", base58::Base58Visitor::new())
+    }
+}
+
 impl fmt::Debug for Pubkey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", bs58::encode(self.0).into_string())
+        let mut buf = [0u8; MAX_BASE58_LEN];
+        f.write_str(self.to_base58_buf(&mut buf))
     }
 }
 
 impl fmt::Display for Pubkey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", bs58::encode(self.0).into_string())
+        let mut buf = [0u8; MAX_BASE58_LEN];
+        f.write_str(self.to_base58_buf(&mut buf))
     }
 }
 
@@ -191,6 +217,22 @@ impl borsh0_9::de::BorshDeserialize for Pubkey {
         Ok(Self(borsh0_9::BorshDeserialize::deserialize(buf)?))
     }
 }
+impl borsh0_10::ser::BorshSerialize for Pubkey {
+    fn serialize<W: borsh0_10::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> ::core::result::Result<(), borsh0_10::maybestd::io::Error> {
+        borsh0_10::BorshSerialize::serialize(&self.0, writer)
+    }
+}
+impl borsh0_9::ser::BorshSerialize for Pubkey {
+    fn serialize<W: borsh0_9::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> ::core::result::Result<(), borsh0_9::maybestd::io::Error> {
+        borsh0_9::BorshSerialize::serialize(&self.0, writer)
+    }
+}
 
 impl Pubkey {
     #[deprecated(
@@ -205,6 +247,28 @@ impl Pubkey {
         Self(pubkey_array)
     }
 
+    /// Decode a base58 string into a `Pubkey` in a const context.
+    ///
+    /// This is what the [`pubkey!`] and [`declare_id!`] macros expand to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not valid base58 or does not decode to exactly 32
+    /// bytes. In a const context this panic is a compile error.
+    ///
+    /// [`pubkey!`]: crate::pubkey!
+    /// [`declare_id!`]: crate::declare_id!
+    pub const fn from_str_const(s: &str) -> Self {
+        if s.len() > MAX_BASE58_LEN {
+            panic!("String is the wrong size");
+        }
+        match base58::decode::<PUBKEY_BYTES>(s.as_bytes()) {
+            Ok(bytes) => Self(bytes),
+            Err(base58::DecodeError::InvalidCharacter) => panic!("Invalid Base58 string"),
+            Err(base58::DecodeError::WrongSize) => panic!("String is the wrong size"),
+        }
+    }
+
     #[deprecated(since = "1.3.9", note = "Please use 'Pubkey::new_unique' instead")]
     pub fn new_rand() -> Self {
         // Consider removing Pubkey::new_rand() entirely in the v1.5 or v1.6 timeframe
@@ -224,9 +288,254 @@ impl Pubkey {
         Self::from(b)
     }
 
+    /// Create an address derived from a base address, a string seed and an owner program.
+    ///
+    /// The address is the SHA-256 hash of `base`, `seed` and `owner`, as used
+    /// by the system program's `*_with_seed` instructions.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PubkeyError::MaxSeedLengthExceeded`] if `seed` is longer than
+    /// [`MAX_SEED_LEN`] bytes, and [`PubkeyError::IllegalOwner`] if `owner`
+    /// ends with the program derived address marker, which would let the
+    /// result collide with a program derived address.
+    pub fn create_with_seed(
+        base: &Pubkey,
+        seed: &str,
+        owner: &Pubkey,
+    ) -> Result<Pubkey, PubkeyError> {
+        if seed.len() > MAX_SEED_LEN {
+            return Err(PubkeyError::MaxSeedLengthExceeded);
+        }
+
+        let owner = owner.as_ref();
+        if owner.len() >= PDA_MARKER.len() {
+            let slice = &owner[owner.len() - PDA_MARKER.len()..];
+            if slice == PDA_MARKER {
+                return Err(PubkeyError::IllegalOwner);
+            }
+        }
+
+        let hash = crate::hash::hashv(&[base.as_ref(), seed.as_ref(), owner]);
+        Ok(Pubkey::from(hash.to_bytes()))
+    }
+
+    /// Create a valid [program derived address][pda] without searching for a bump seed.
+    ///
+    /// [pda]: https://solana.com/docs/core/cpi#program-derived-addresses
+    ///
+    /// The address is the SHA-256 hash of the `seeds`, the `program_id` and the
+    /// `"ProgramDerivedAddress"` marker. Because a program derived address must
+    /// not have an associated private key, candidates that lie on the ed25519
+    /// curve are rejected with [`PubkeyError::InvalidSeeds`]; callers are
+    /// expected to pick a bump seed for which this does not happen, usually
+    /// with [`find_program_address`].
+    ///
+    /// [`find_program_address`]: Pubkey::find_program_address
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PubkeyError::MaxSeedLengthExceeded`] if there are more than
+    /// [`MAX_SEEDS`] seeds or any seed is longer than [`MAX_SEED_LEN`], and
+    /// [`PubkeyError::InvalidSeeds`] if the resulting address is on the curve.
+    pub fn create_program_address(
+        seeds: &[&[u8]],
+        program_id: &Pubkey,
+    ) -> Result<Pubkey, PubkeyError> {
+        if seeds.len() > MAX_SEEDS {
+            return Err(PubkeyError::MaxSeedLengthExceeded);
+        }
+        for seed in seeds.iter() {
+            if seed.len() > MAX_SEED_LEN {
+                return Err(PubkeyError::MaxSeedLengthExceeded);
+            }
+        }
+
+        let mut hasher = crate::hash::Hasher::default();
+        for seed in seeds.iter() {
+            hasher.hash(seed);
+        }
+        hasher.hashv(&[program_id.as_ref(), PDA_MARKER]);
+        let hash = hasher.result();
+
+        if bytes_are_curve_point(hash) {
+            return Err(PubkeyError::InvalidSeeds);
+        }
+
+        Ok(Pubkey::from(hash.to_bytes()))
+    }
+
+    /// Find a valid [program derived address][pda] and its corresponding bump seed.
+    ///
+    /// [pda]: https://solana.com/docs/core/cpi#program-derived-addresses
+    ///
+    /// Starting from a bump seed of 255 and counting down, the bump seed is
+    /// appended to `seeds` and passed to [`create_program_address`] until an
+    /// off-curve address is found. The first such address and its bump seed
+    /// are returned, which matches the canonical bump chosen by Solana.
+    ///
+    /// [`create_program_address`]: Pubkey::create_program_address
+    ///
+    /// # Panics
+    ///
+    /// Panics in the statistically improbable event that no bump seed yields a
+    /// valid address, or if the seeds are too long or too many. Use
+    /// [`try_find_program_address`] to handle those cases.
+    ///
+    /// [`try_find_program_address`]: Pubkey::try_find_program_address
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+        Self::try_find_program_address(seeds, program_id)
+            .unwrap_or_else(|| panic!("Unable to find a viable program address bump seed"))
+    }
+
+    /// Find a valid [program derived address][pda] and its corresponding bump seed.
+    ///
+    /// [pda]: https://solana.com/docs/core/cpi#program-derived-addresses
+    ///
+    /// The same as [`find_program_address`], but returns `None` instead of
+    /// panicking when no address can be derived.
+    ///
+    /// [`find_program_address`]: Pubkey::find_program_address
+    pub fn try_find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<(Pubkey, u8)> {
+        let mut bump_seed = [u8::MAX];
+        for _ in 0..u8::MAX {
+            {
+                let mut seeds_with_bump = seeds.to_vec();
+                seeds_with_bump.push(&bump_seed);
+                match Self::create_program_address(&seeds_with_bump, program_id) {
+                    Ok(address) => return Some((address, bump_seed[0])),
+                    Err(PubkeyError::InvalidSeeds) => (),
+                    _ => break,
+                }
+            }
+            bump_seed[0] -= 1;
+        }
+        None
+    }
+
     pub const fn to_bytes(self) -> [u8; 32] {
         self.0
     }
+
+    /// Base58-encode this pubkey into `buf` without allocating.
+    ///
+    /// Returns the encoded prefix of `buf`, which is the same string as
+    /// produced by the `Display` impl.
+    pub fn to_base58_buf<'a>(&self, buf: &'a mut [u8; MAX_BASE58_LEN]) -> &'a str {
+        let len = base58::encode(&self.0, buf);
+        std::str::from_utf8(&buf[..len]).expect("base58 alphabet is ASCII")
+    }
+
+    /// Returns whether this pubkey is a valid ed25519 public key.
+    ///
+    /// The 32 bytes are decompressed as an Edwards point; program derived
+    /// addresses are guaranteed to fail this check and so can never sign.
+    pub fn is_on_curve(&self) -> bool {
+        bytes_are_curve_point(self)
+    }
+}
+
+/// Convenience macro to define a static public key.
+///
+/// Input: a single literal base58 string representation of a pubkey. The
+/// string is decoded at compile time, so malformed or wrong-length input is a
+/// compile error.
+///
+/// # Example
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// use twine_solana_sdk::pubkey;
+/// use twine_solana_sdk::pubkey::Pubkey;
+///
+/// static ID: Pubkey = pubkey!("My11111111111111111111111111111111111111111");
+///
+/// let my_id = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+/// assert_eq!(ID, my_id);
+/// ```
+///
+/// ```compile_fail
+/// use twine_solana_sdk::pubkey;
+/// use twine_solana_sdk::pubkey::Pubkey;
+///
+/// // `I` is not part of the base58 alphabet
+/// static ID: Pubkey = pubkey!("MyI1111111111111111111111111111111111111111");
+/// ```
+#[macro_export]
+macro_rules! pubkey {
+    ($input:literal) => {{
+        const PUBKEY: $crate::pubkey::Pubkey = $crate::pubkey::Pubkey::from_str_const($input);
+        PUBKEY
+    }};
+}
+
+/// Convenience macro to declare a static public key and functions to interact with it.
+///
+/// Input: a single literal base58 string representation of a program's ID.
+///
+/// Defines a `pub const ID`, a `pub const fn id()` returning it and a
+/// `pub fn check_id(&Pubkey)` comparing against it.
+///
+/// # Example
+///
+/// ```
+/// # // wrapper is used so that the macro invocation occurs in the item position
+/// # // rather than in the statement position which isn't allowed.
+/// use std::str::FromStr;
+///
+/// use twine_solana_sdk::declare_id;
+/// use twine_solana_sdk::pubkey::Pubkey;
+///
+/// # mod item_wrapper {
+/// #   use twine_solana_sdk::declare_id;
+/// declare_id!("My11111111111111111111111111111111111111111");
+/// # }
+/// # use item_wrapper::id;
+///
+/// let my_id = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+/// assert_eq!(id(), my_id);
+/// ```
+///
+/// ```compile_fail
+/// use twine_solana_sdk::declare_id;
+///
+/// // decodes to 31 bytes
+/// declare_id!("1111111111111111111111111111111");
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! declare_id {
+    ($address:expr) => {
+        /// The const program ID.
+        pub const ID: $crate::pubkey::Pubkey = $crate::pubkey::Pubkey::from_str_const($address);
+
+        /// Returns `true` if given pubkey is the program ID.
+        pub fn check_id(id: &$crate::pubkey::Pubkey) -> bool {
+            id == &ID
+        }
+
+        /// Returns the program ID.
+        pub const fn id() -> $crate::pubkey::Pubkey {
+            ID
+        }
+
+        #[cfg(test)]
+        #[test]
+        fn test_id() {
+            assert!(check_id(&id()));
+        }
+    };
+}
+
+/// Returns whether `bytes` decompress to a point on the ed25519 curve.
+pub fn bytes_are_curve_point<T: AsRef<[u8]>>(bytes: T) -> bool {
+    let Ok(compressed_edwards_y) =
+        curve25519_dalek::edwards::CompressedEdwardsY::from_slice(bytes.as_ref())
+    else {
+        return false;
+    };
+    compressed_edwards_y.decompress().is_some()
 }
 
 #[cfg(test)]
@@ -277,4 +586,313 @@ mod tests {
         too_long.push('1');
         assert_eq!(too_long.parse::<Pubkey>(), Err(ParsePubkeyError::WrongSize));
     }
+
+    #[test]
+    fn test_pubkey_to_base58_buf() {
+        let mut buf = [0u8; MAX_BASE58_LEN];
+        for pubkey in [
+            Pubkey::default(),
+            Pubkey::from([255u8; PUBKEY_BYTES]),
+            Pubkey::new_unique(),
+            Pubkey::from(rand::random::<[u8; 32]>()),
+        ] {
+            let encoded = bs58::encode(pubkey.0).into_string();
+            assert_eq!(pubkey.to_base58_buf(&mut buf), encoded);
+            assert_eq!(pubkey.to_string(), encoded);
+            assert_eq!(format!("{pubkey:?}"), encoded);
+        }
+    }
+
+    #[test]
+    fn test_pubkey_serde() {
+        let pubkey = Pubkey::new_unique();
+
+        let json = serde_json::to_string(&pubkey).unwrap();
+        assert_eq!(json, format!("\"{pubkey}\""));
+        assert_eq!(serde_json::from_str::<Pubkey>(&json).unwrap(), pubkey);
+
+        // the byte array form is still accepted in human-readable formats
+        let json = serde_json::to_string(&pubkey.0).unwrap();
+        assert_eq!(serde_json::from_str::<Pubkey>(&json).unwrap(), pubkey);
+
+        assert!(serde_json::from_str::<Pubkey>("\"I\"").is_err());
+        assert!(serde_json::from_str::<Pubkey>("[1, 2, 3]").is_err());
+
+        // binary formats are unchanged
+        let bytes = bincode::serialize(&pubkey).unwrap();
+        assert_eq!(bytes, pubkey.0);
+        assert_eq!(bincode::deserialize::<Pubkey>(&bytes).unwrap(), pubkey);
+    }
+
+    #[test]
+    fn test_pubkey_borsh() {
+        let pubkey = Pubkey::new_unique();
+
+        let bytes = borsh::to_vec(&pubkey).unwrap();
+        assert_eq!(bytes, pubkey.0);
+        assert_eq!(borsh::from_slice::<Pubkey>(&bytes).unwrap(), pubkey);
+
+        assert_eq!(borsh0_10::to_vec(&pubkey).unwrap(), bytes);
+        assert_eq!(
+            <Pubkey as borsh0_10::BorshDeserialize>::try_from_slice(&bytes).unwrap(),
+            pubkey
+        );
+
+        assert_eq!(
+            borsh0_9::BorshSerialize::try_to_vec(&pubkey).unwrap(),
+            bytes
+        );
+        assert_eq!(
+            <Pubkey as borsh0_9::BorshDeserialize>::try_from_slice(&bytes).unwrap(),
+            pubkey
+        );
+    }
+
+    #[test]
+    fn test_pubkey_borsh_schema() {
+        use borsh::schema::{BorshSchemaContainer, Definition};
+
+        let container = BorshSchemaContainer::for_type::<Pubkey>();
+        assert_eq!(container.declaration(), "Pubkey");
+        assert_eq!(
+            container.get_definition("Pubkey"),
+            Some(&Definition::Struct {
+                fields: borsh::schema::Fields::UnnamedFields(vec!["[u8; 32]".to_string()])
+            })
+        );
+    }
+
+    #[test]
+    fn test_pubkey_macro() {
+        const PK: Pubkey = crate::pubkey!("9h1HyLCW5dZnBVap8C5egQ9Z6pHyjsh5MNy83iPqqRuq");
+        assert_eq!(
+            PK,
+            Pubkey::from_str("9h1HyLCW5dZnBVap8C5egQ9Z6pHyjsh5MNy83iPqqRuq").unwrap()
+        );
+        assert_eq!(
+            crate::pubkey!("11111111111111111111111111111111"),
+            Pubkey::default()
+        );
+    }
+
+    mod declared {
+        crate::declare_id!("BPFLoaderUpgradeab1e11111111111111111111111");
+    }
+
+    #[test]
+    fn test_declare_id() {
+        let loader = Pubkey::from_str("BPFLoaderUpgradeab1e11111111111111111111111").unwrap();
+        assert_eq!(declared::ID, loader);
+        assert_eq!(declared::id(), loader);
+        assert!(declared::check_id(&loader));
+        assert!(!declared::check_id(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Base58 string")]
+    fn test_from_str_const_invalid() {
+        Pubkey::from_str_const("BPFLoaderUpgradeab1eI1111111111111111111111");
+    }
+
+    #[test]
+    #[should_panic(expected = "String is the wrong size")]
+    fn test_from_str_const_wrong_size() {
+        Pubkey::from_str_const("BPFLoaderUpgradeab1e1111111111111111111111");
+    }
+
+    #[test]
+    fn test_create_with_seed() {
+        assert!(
+            Pubkey::create_with_seed(&Pubkey::new_unique(), "☉", &Pubkey::new_unique()).is_ok()
+        );
+        assert_eq!(
+            Pubkey::create_with_seed(
+                &Pubkey::new_unique(),
+                std::str::from_utf8(&[127; MAX_SEED_LEN + 1]).unwrap(),
+                &Pubkey::new_unique()
+            ),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        assert!(Pubkey::create_with_seed(
+            &Pubkey::new_unique(),
+            "\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}",
+            &Pubkey::new_unique()
+        )
+        .is_ok());
+        // utf-8 abuse ;)
+        assert_eq!(
+            Pubkey::create_with_seed(
+                &Pubkey::new_unique(),
+                "x\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}\u{10FFFF}",
+                &Pubkey::new_unique()
+            ),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        assert!(Pubkey::create_with_seed(
+            &Pubkey::new_unique(),
+            std::str::from_utf8(&[0; MAX_SEED_LEN]).unwrap(),
+            &Pubkey::new_unique(),
+        )
+        .is_ok());
+        assert!(Pubkey::create_with_seed(&Pubkey::new_unique(), "", &Pubkey::new_unique()).is_ok());
+        assert_eq!(
+            Pubkey::create_with_seed(
+                &Pubkey::default(),
+                "limber chicken: 4/45",
+                &Pubkey::default(),
+            ),
+            Ok("9h1HyLCW5dZnBVap8C5egQ9Z6pHyjsh5MNy83iPqqRuq"
+                .parse()
+                .unwrap())
+        );
+    }
+
+    #[test]
+    fn test_create_with_seed_illegal_owner() {
+        let mut owner = [0u8; PUBKEY_BYTES];
+        owner[PUBKEY_BYTES - PDA_MARKER.len()..].copy_from_slice(PDA_MARKER);
+        assert_eq!(
+            Pubkey::create_with_seed(&Pubkey::new_unique(), "seed", &Pubkey::from(owner)),
+            Err(PubkeyError::IllegalOwner)
+        );
+        assert_eq!(PubkeyError::from(2), PubkeyError::IllegalOwner);
+    }
+
+    #[test]
+    fn test_create_program_address() {
+        let exceeded_seed = &[127; MAX_SEED_LEN + 1];
+        let max_seed = &[0; MAX_SEED_LEN];
+        let exceeded_seeds: &[&[u8]] = &[
+            &[1],
+            &[2],
+            &[3],
+            &[4],
+            &[5],
+            &[6],
+            &[7],
+            &[8],
+            &[9],
+            &[10],
+            &[11],
+            &[12],
+            &[13],
+            &[14],
+            &[15],
+            &[16],
+            &[17],
+        ];
+        let max_seeds: &[&[u8]] = &[
+            &[1],
+            &[2],
+            &[3],
+            &[4],
+            &[5],
+            &[6],
+            &[7],
+            &[8],
+            &[9],
+            &[10],
+            &[11],
+            &[12],
+            &[13],
+            &[14],
+            &[15],
+            &[16],
+        ];
+        let program_id = Pubkey::from_str("BPFLoaderUpgradeab1e11111111111111111111111").unwrap();
+        let public_key = Pubkey::from_str("SeedPubey1111111111111111111111111111111111").unwrap();
+
+        assert_eq!(
+            Pubkey::create_program_address(&[exceeded_seed], &program_id),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        assert_eq!(
+            Pubkey::create_program_address(&[b"short_seed", exceeded_seed], &program_id),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        assert!(Pubkey::create_program_address(&[max_seed], &program_id).is_ok());
+        assert_eq!(
+            Pubkey::create_program_address(exceeded_seeds, &program_id),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        assert!(Pubkey::create_program_address(max_seeds, &program_id).is_ok());
+        assert_eq!(
+            Pubkey::create_program_address(&[b"", &[1]], &program_id),
+            Ok("BwqrghZA2htAcqq8dzP1WDAhTXYTYWj7CHxF5j7TDBAe"
+                .parse()
+                .unwrap())
+        );
+        assert_eq!(
+            Pubkey::create_program_address(&["☉".as_ref(), &[0]], &program_id),
+            Ok("13yWmRpaTR4r5nAktwLqMpRNr28tnVUZw26rTvPSSB19"
+                .parse()
+                .unwrap())
+        );
+        assert_eq!(
+            Pubkey::create_program_address(&[b"Talking", b"Squirrels"], &program_id),
+            Ok("2fnQrngrQT4SeLcdToJAD96phoEjNL2man2kfRLCASVk"
+                .parse()
+                .unwrap())
+        );
+        assert_eq!(
+            Pubkey::create_program_address(&[public_key.as_ref(), &[1]], &program_id),
+            Ok("976ymqVnfE32QFe6NfGDctSvVa36LWnvYxhU6G2232YL"
+                .parse()
+                .unwrap())
+        );
+        assert_ne!(
+            Pubkey::create_program_address(&[b"Talking", b"Squirrels"], &program_id).unwrap(),
+            Pubkey::create_program_address(&[b"Talking"], &program_id).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_pubkey_off_curve() {
+        // try a bunch of random input, all successful generated program
+        // addresses must land off the curve and be unique
+        let mut addresses = std::vec::Vec::new();
+        for _ in 0..1_000 {
+            let program_id = Pubkey::new_unique();
+            let bytes1 = rand::random::<[u8; 10]>();
+            let bytes2 = rand::random::<[u8; 32]>();
+            if let Ok(program_address) =
+                Pubkey::create_program_address(&[&bytes1, &bytes2], &program_id)
+            {
+                assert!(!bytes_are_curve_point(program_address));
+                assert!(!addresses.contains(&program_address));
+                addresses.push(program_address);
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_on_curve() {
+        let on_curve =
+            Pubkey::from(curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED.to_bytes());
+        assert!(on_curve.is_on_curve());
+
+        let program_id = Pubkey::from_str("BPFLoaderUpgradeab1e11111111111111111111111").unwrap();
+        let off_curve =
+            Pubkey::create_program_address(&[b"Talking", b"Squirrels"], &program_id).unwrap();
+        assert!(!off_curve.is_on_curve());
+
+        // y = 2 has no corresponding x on the curve
+        let mut not_a_point = [0u8; 32];
+        not_a_point[0] = 2;
+        assert!(!Pubkey::from(not_a_point).is_on_curve());
+    }
+
+    #[test]
+    fn test_find_program_address() {
+        for _ in 0..1_000 {
+            let program_id = Pubkey::new_unique();
+            let (address, bump_seed) =
+                Pubkey::find_program_address(&[b"Lil'", b"Bits"], &program_id);
+            assert_eq!(
+                address,
+                Pubkey::create_program_address(&[b"Lil'", b"Bits", &[bump_seed]], &program_id)
+                    .unwrap()
+            );
+        }
+    }
 }